@@ -0,0 +1,470 @@
+use crate::compiler::{
+    FrontmatterError, SiteConfig, held_back_reason, is_local_url, load_config, markdown_files,
+    markdown_options, output_rel_path, parse_post, strip_query, today,
+};
+use anyhow::{Result, bail};
+use gray_matter::engine::YAML;
+use gray_matter::{Matter, ParsedEntity, Pod};
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event, Parser, Tag};
+use sinter_core::PostMetadata;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+pub struct CheckOptions<'a> {
+    pub posts_dir: &'a Path,
+    pub archives_dir: &'a Path,
+    pub config_path: &'a Path,
+    /// Directory that root-relative asset URLs (`/images/a.png`) resolve against.
+    pub static_dir: &'a Path,
+    /// Count drafts as link targets, as `sinter build --drafts` publishes them.
+    pub drafts: bool,
+    /// Count posts dated after today as link targets, as `--future` does.
+    pub future: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file.display(), line, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// A collection of posts and the URL prefix its posts are routed under.
+struct Collection<'a> {
+    dir: &'a Path,
+    prefix: &'static str,
+    route: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReferenceKind {
+    Link,
+    Image,
+}
+
+struct Reference {
+    kind: ReferenceKind,
+    url: String,
+    line: usize,
+}
+
+struct CheckedPost {
+    file: PathBuf,
+    metadata: PostMetadata,
+    references: Vec<Reference>,
+}
+
+/// Runs every check and logs the findings. Fails if any error was found.
+pub fn check(options: &CheckOptions) -> Result<()> {
+    let findings = run_checks(options)?;
+
+    for finding in &findings {
+        match finding.severity {
+            Severity::Error => error!("{}", finding),
+            Severity::Warning => warn!("{}", finding),
+        }
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+    info!(
+        "Check finished: {} error(s), {} warning(s).",
+        errors, warnings
+    );
+
+    if errors > 0 {
+        bail!("Content check failed with {} error(s)", errors);
+    }
+    Ok(())
+}
+
+pub fn run_checks(options: &CheckOptions) -> Result<Vec<Finding>> {
+    // Validates the configuration exactly like a build would.
+    let (config, _) = load_config(options.config_path)?;
+
+    let collections = [
+        Collection {
+            dir: options.posts_dir,
+            prefix: "posts",
+            route: "/posts/",
+        },
+        Collection {
+            dir: options.archives_dir,
+            prefix: "archives",
+            route: "/archives/posts/",
+        },
+    ];
+
+    let mut findings = Vec::new();
+    let mut posts = Vec::new();
+    // Route prefix -> slugs that resolve to a generated JSON file.
+    let mut routes: HashMap<&str, HashSet<String>> = HashMap::new();
+    // Route prefix -> slug -> why the build leaves that post out.
+    let mut held_back: HashMap<&str, HashMap<String, String>> = HashMap::new();
+    let today = today();
+    // Generated JSON file -> the Markdown file it is built from.
+    let mut outputs: HashMap<String, PathBuf> = HashMap::new();
+
+    for collection in &collections {
+        let known = routes.entry(collection.route).or_default();
+        let held = held_back.entry(collection.route).or_default();
+        if !collection.dir.exists() {
+            continue;
+        }
+
+        for path in markdown_files(collection.dir) {
+            let Some(post) = check_file(&path, &config, &mut findings) else {
+                continue;
+            };
+
+            // The client fetches `<prefix>/<slug>.json`, so the file name must match the slug.
+            let output = output_rel_path(collection.dir, &path, collection.prefix);
+            if let Some(first) = outputs.get(&output) {
+                // The build skips this file, exactly as reported here.
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    file: path.clone(),
+                    line: None,
+                    message: format!(
+                        "output `{}` is already generated from {}",
+                        output,
+                        first.display()
+                    ),
+                });
+                continue;
+            }
+            outputs.insert(output.clone(), path.clone());
+            let routed_slug = output
+                .strip_prefix(collection.prefix)
+                .and_then(|s| s.strip_prefix('/'))
+                .and_then(|s| s.strip_suffix(".json"))
+                .unwrap_or_default()
+                .to_string();
+            if routed_slug != post.metadata.slug {
+                findings.push(Finding {
+                    severity: Severity::Warning,
+                    file: path.clone(),
+                    line: None,
+                    message: format!(
+                        "slug `{}` does not match the file name; the post is served at `{}{}`",
+                        post.metadata.slug, collection.route, routed_slug
+                    ),
+                });
+            }
+            // Held-back posts are still checked, but links to them would 404.
+            match held_back_reason(&post.metadata, options.drafts, options.future, &today) {
+                Some(reason) => {
+                    held.insert(routed_slug, reason);
+                }
+                None => {
+                    known.insert(routed_slug);
+                }
+            }
+            posts.push(post);
+        }
+    }
+
+    check_duplicates(&posts, "id", |m| &m.id, &mut findings);
+    check_duplicates(&posts, "slug", |m| &m.slug, &mut findings);
+
+    for post in &posts {
+        for reference in &post.references {
+            let message = match reference.kind {
+                ReferenceKind::Link => check_link(&reference.url, &routes, &held_back),
+                ReferenceKind::Image => check_image(&reference.url, &post.file, options.static_dir),
+            };
+            if let Some(message) = message {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    file: post.file.clone(),
+                    line: Some(reference.line),
+                    message,
+                });
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+fn check_file(
+    path: &Path,
+    config: &SiteConfig,
+    findings: &mut Vec<Finding>,
+) -> Option<CheckedPost> {
+    let mut fail = |message: String, line: Option<usize>| {
+        findings.push(Finding {
+            severity: Severity::Error,
+            file: path.to_path_buf(),
+            line,
+            message,
+        });
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            fail(format!("failed to read file: {}", e), None);
+            return None;
+        }
+    };
+
+    let post = match parse_post(&source, config) {
+        Ok(post) => post,
+        Err(e) => {
+            // Frontmatter lines are relative to the YAML block, which starts after `---`.
+            let line = e
+                .downcast_ref::<FrontmatterError>()
+                .and_then(|fm| fm.line)
+                .map(|line| line + 1);
+            fail(format!("{:#}", e), line);
+            return None;
+        }
+    };
+
+    Some(CheckedPost {
+        file: path.to_path_buf(),
+        metadata: post.metadata,
+        references: collect_references(&source),
+    })
+}
+
+/// Scans the Markdown body for links and images, recording their line in the
+/// original file (frontmatter included).
+fn collect_references(source: &str) -> Vec<Reference> {
+    let matter = Matter::<YAML>::new();
+    let parsed: Result<ParsedEntity<Pod>, _> = matter.parse(source);
+    let body = match parsed {
+        Ok(entity) => entity.content,
+        Err(_) => return Vec::new(),
+    };
+    let body_offset = source.rfind(body.as_str()).unwrap_or(0);
+
+    Parser::new_ext(&body, markdown_options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| {
+            let (kind, url) = match event {
+                Event::Start(Tag::Link { dest_url, .. }) => (ReferenceKind::Link, dest_url),
+                Event::Start(Tag::Image { dest_url, .. }) => (ReferenceKind::Image, dest_url),
+                _ => return None,
+            };
+            Some(Reference {
+                kind,
+                url: url.to_string(),
+                line: line_of(source, body_offset + range.start),
+            })
+        })
+        .collect()
+}
+
+fn check_duplicates<F>(posts: &[CheckedPost], field: &str, key: F, findings: &mut Vec<Finding>)
+where
+    F: Fn(&PostMetadata) -> &String,
+{
+    let mut seen: HashMap<&str, &Path> = HashMap::new();
+    for post in posts {
+        let value = key(&post.metadata);
+        if let Some(first) = seen.get(value.as_str()) {
+            findings.push(Finding {
+                severity: Severity::Error,
+                file: post.file.clone(),
+                line: None,
+                message: format!(
+                    "duplicate {} `{}` (already used by {})",
+                    field,
+                    value,
+                    first.display()
+                ),
+            });
+        } else {
+            seen.insert(value, &post.file);
+        }
+    }
+}
+
+fn check_link(
+    url: &str,
+    routes: &HashMap<&str, HashSet<String>>,
+    held_back: &HashMap<&str, HashMap<String, String>>,
+) -> Option<String> {
+    let path = strip_query(url);
+    // `/archives/posts/` is checked first; it does not share a prefix with `/posts/`.
+    for route in ["/archives/posts/", "/posts/"] {
+        if let Some(slug) = path.strip_prefix(route) {
+            let slug = percent_decode_str(slug.trim_matches('/')).decode_utf8_lossy();
+            if routes.get(route).is_some_and(|s| s.contains(slug.as_ref())) {
+                return None;
+            }
+            let reason = held_back.get(route).and_then(|h| h.get(slug.as_ref()));
+            return Some(match reason {
+                Some(reason) => format!(
+                    "broken link `{}`: post `{}` is held back from the build ({})",
+                    url, slug, reason
+                ),
+                None => format!("broken link `{}`: no post with slug `{}`", url, slug),
+            });
+        }
+    }
+    None
+}
+
+fn check_image(url: &str, post_file: &Path, static_dir: &Path) -> Option<String> {
+    if !is_local_url(url) {
+        return None;
+    }
+
+    let path = percent_decode_str(strip_query(url)).decode_utf8_lossy();
+    let resolved = match path.strip_prefix('/') {
+        Some(rooted) => static_dir.join(rooted),
+        None => post_file
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(path.as_ref()),
+    };
+
+    (!resolved.exists()).then(|| {
+        format!(
+            "image `{}` not found (looked for {})",
+            url,
+            resolved.display()
+        )
+    })
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_post(dir: &Path, name: &str, id: &str, slug: &str, body: &str) {
+        let content = format!(
+            "---\nid: \"{id}\"\ntitle: \"T\"\nslug: \"{slug}\"\ndate: \"2024-01-01\"\nsummary: \"s\"\n---\n{body}"
+        );
+        fs::write(dir.join(name), content).unwrap();
+    }
+
+    #[test]
+    fn test_run_checks_reports_problems() {
+        let root = tempfile::tempdir().unwrap();
+        let posts = root.path().join("posts");
+        let archives = root.path().join("archives");
+        fs::create_dir_all(&posts).unwrap();
+        fs::create_dir_all(&archives).unwrap();
+        let config = root.path().join("sinter.toml");
+        fs::write(
+            &config,
+            "[site]\ntitle = \"t\"\nsubtitle = \"s\"\ndescription = \"d\"\n\n[home]\n",
+        )
+        .unwrap();
+
+        write_post(
+            &posts,
+            "a.md",
+            "1",
+            "a",
+            "Intro\n\nSee [b](/archives/posts/b) and [missing](/posts/nope).\n\n![img](./missing.png)\n",
+        );
+        write_post(&archives, "b.md", "1", "b", "Archived.\n");
+        fs::create_dir_all(archives.join("b")).unwrap();
+        write_post(&archives, "b/index.md", "1", "b", "Bundled.\n");
+
+        let findings = run_checks(&CheckOptions {
+            posts_dir: &posts,
+            archives_dir: &archives,
+            config_path: &config,
+            static_dir: root.path(),
+            drafts: false,
+            future: false,
+        })
+        .unwrap();
+
+        let messages: Vec<String> = findings.iter().map(|f| f.message.clone()).collect();
+        assert_eq!(findings.len(), 4, "{:#?}", messages);
+        assert!(messages.iter().any(|m| m.starts_with("duplicate id `1`")));
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.starts_with("output `archives/b.json` is already generated from"))
+        );
+
+        let link = findings
+            .iter()
+            .find(|f| f.message.contains("/posts/nope"))
+            .unwrap();
+        assert_eq!(link.line, Some(10));
+
+        let image = findings
+            .iter()
+            .find(|f| f.message.contains("missing.png"))
+            .unwrap();
+        assert_eq!(image.line, Some(12));
+    }
+
+    #[test]
+    fn test_link_to_draft_is_broken_unless_drafts_are_built() {
+        let root = tempfile::tempdir().unwrap();
+        let posts = root.path().join("posts");
+        let archives = root.path().join("archives");
+        fs::create_dir_all(&posts).unwrap();
+        let config = root.path().join("sinter.toml");
+        fs::write(
+            &config,
+            "[site]\ntitle = \"t\"\nsubtitle = \"s\"\ndescription = \"d\"\n\n[home]\n",
+        )
+        .unwrap();
+
+        write_post(&posts, "a.md", "1", "a", "See [wip](/posts/wip).\n");
+        fs::write(
+            posts.join("wip.md"),
+            "---\nid: \"2\"\ntitle: \"T\"\nslug: \"wip\"\ndate: \"2024-01-01\"\nsummary: \"s\"\ndraft: true\n---\nNot yet.\n",
+        )
+        .unwrap();
+
+        let check = |drafts: bool| {
+            run_checks(&CheckOptions {
+                posts_dir: &posts,
+                archives_dir: &archives,
+                config_path: &config,
+                static_dir: root.path(),
+                drafts,
+                future: false,
+            })
+            .unwrap()
+        };
+
+        let findings = check(false);
+        assert_eq!(findings.len(), 1, "{:#?}", findings);
+        assert_eq!(
+            findings[0].message,
+            "broken link `/posts/wip`: post `wip` is held back from the build (draft)"
+        );
+
+        assert!(check(true).is_empty());
+    }
+}
@@ -1,49 +1,307 @@
+use crate::{compress, deploy};
 use anyhow::{Context, Result};
+use assets::AssetPipeline;
+pub(crate) use assets::{is_local_url, strip_query};
+use cache::BuildCache;
+use chrono::Datelike;
+use diagnostics::Diagnostic;
+pub(crate) use diagnostics::FrontmatterError;
 use gray_matter::engine::YAML;
 use gray_matter::{Matter, ParsedEntity, Pod};
+use highlight::Highlighter;
 use pulldown_cmark::{Options, Parser};
 use rayon::prelude::*;
-use serde::Deserialize;
-use sinter_core::constants::{DEFAULT_POSTS_PER_PAGE, PAGES_DIR, SITE_DATA_FILENAME};
-use sinter_core::{PageData, Post, PostMetadata, SiteMetaData, SitePostMetadata};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sinter_core::constants::{
+    DEFAULT_POSTS_PER_PAGE, PAGES_DIR, SEARCH_DIR, SITE_DATA_FILENAME, TAGS_DIR,
+    TAGS_INDEX_FILENAME,
+};
+use sinter_core::encoding::{self, DataFormat};
+use sinter_core::{
+    LiteDate, PageData, Post, PostMetadata, SiteMetaData, SitePostMetadata, TagIndex, TagSummary,
+    tag_slug,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use walkdir::WalkDir;
 
-#[derive(Debug, Deserialize)]
-struct SiteConfig {
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct SiteConfig {
     pub site: SiteSection,
     pub home: HomeSection,
+    #[serde(default)]
+    pub build: BuildSection,
+    #[serde(default)]
+    pub search: SearchSection,
+    #[serde(default)]
+    pub feed: FeedSection,
+    #[serde(default)]
+    pub sitemap: SitemapSection,
+    #[serde(default)]
+    pub highlight: HighlightSection,
+    #[serde(default)]
+    pub admonitions: AdmonitionSection,
+    #[serde(default)]
+    pub reading: ReadingSection,
+    #[serde(default)]
+    pub summary: SummarySection,
+    #[serde(default)]
+    pub assets: AssetsSection,
 }
 
-#[derive(Debug, Deserialize)]
-struct SiteSection {
+impl SiteConfig {
+    /// Absolute URL the site is served from: `[sitemap] base_url`, falling back
+    /// to `[feed] site_url`.
+    pub fn base_url(&self) -> Option<&str> {
+        self.sitemap
+            .base_url
+            .as_deref()
+            .or(self.feed.site_url.as_deref())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct SiteSection {
     pub title: String,
     pub subtitle: String,
     pub description: String,
 }
 
-#[derive(Debug, Deserialize)]
-struct HomeSection {
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct HomeSection {
     pub posts_per_page: Option<usize>,
 }
 
-pub fn compile(
-    posts_dir: &Path,
-    archives_dir: &Path,
-    data_output_dir: &Path,
-    config_path: &Path,
-) -> Result<()> {
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct BuildSection {
+    /// Fail the build if any post cannot be read or parsed.
+    #[serde(default)]
+    pub strict: bool,
+    /// Write JSON shards under content-hashed names listed in a manifest.
+    #[serde(default)]
+    pub hashed_filenames: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct SearchSection {
+    /// Emit the full-text search index under `search/`.
+    pub enabled: bool,
+    /// Number of term shards; the client only downloads the ones it needs.
+    pub shards: u32,
+    /// Index CJK text as overlapping character pairs instead of single characters.
+    pub cjk_bigrams: bool,
+}
+
+impl Default for SearchSection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            shards: 8,
+            cjk_bigrams: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct FeedSection {
+    /// Emit `feed.xml` (RSS 2.0), `atom.xml` and `feed.json`.
+    pub enabled: bool,
+    /// Number of newest posts included in each feed.
+    pub limit: usize,
+    /// Publish the whole post as HTML instead of only its summary.
+    pub full_content: bool,
+    /// Absolute URL the site is served from, e.g. `https://example.com`.
+    /// Feeds need absolute links, so they are skipped while this is unset.
+    pub site_url: Option<String>,
+}
+
+impl Default for FeedSection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            limit: 20,
+            full_content: true,
+            site_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct HighlightSection {
+    /// Tokenise fenced code blocks at build time.
+    pub enabled: bool,
+    /// Allowlist of languages to highlight, by name or file extension
+    /// (`rust`, `py`); empty means every bundled grammar. It only decides
+    /// which code blocks get tokens: every grammar stays in the binary.
+    pub languages: Vec<String>,
+}
+
+impl Default for HighlightSection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            languages: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct AdmonitionSection {
+    /// Custom `> [!KIND]` callout kinds, besides GitHub's NOTE, TIP, IMPORTANT,
+    /// WARNING and CAUTION, mapped to their default title.
+    #[serde(default)]
+    pub kinds: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct ReadingSection {
+    /// Reading speed for space-separated text, used for `reading_time`.
+    pub words_per_minute: u32,
+    /// Reading speed for CJK text, counted in characters.
+    pub cjk_chars_per_minute: u32,
+}
+
+impl Default for ReadingSection {
+    fn default() -> Self {
+        Self {
+            words_per_minute: 200,
+            cjk_chars_per_minute: 400,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct SummarySection {
+    /// Length limit, in characters, of summaries generated for posts whose
+    /// frontmatter has none.
+    pub max_chars: usize,
+}
+
+impl Default for SummarySection {
+    fn default() -> Self {
+        Self { max_chars: 150 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct AssetsSection {
+    /// Widths, in pixels, of the resized copies generated for local PNG and
+    /// JPEG images and listed in their `srcset`. Empty disables resizing.
+    pub responsive_widths: Vec<u32>,
+    /// Embed a tiny blurred preview of every local image in the AST.
+    pub placeholders: bool,
+}
+
+impl Default for AssetsSection {
+    fn default() -> Self {
+        Self {
+            responsive_widths: Vec::new(),
+            placeholders: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct SitemapSection {
+    /// Emit `sitemap.xml` and `robots.txt`.
+    pub enabled: bool,
+    /// Absolute URL of the site; defaults to `[feed] site_url`.
+    pub base_url: Option<String>,
+    /// Route groups to leave out of the sitemap.
+    pub exclude: Vec<SitemapCollection>,
+}
+
+impl Default for SitemapSection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            base_url: None,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SitemapCollection {
+    /// `/posts/<slug>`
+    Posts,
+    /// `/archives/posts/<slug>`
+    Archives,
+    /// `/` and `/?page=N`
+    Home,
+    /// `/archives` and `/archives?page=N`
+    ArchivePages,
+    /// `/tags/<tag>` and `/tags/<tag>?page=N`
+    Tags,
+}
+
+/// URL path the data output directory is served under by `sinter_web`.
+const DATA_URL_PATH: &str = "/sinter_data";
+
+pub struct CompileOptions<'a> {
+    pub posts_dir: &'a Path,
+    pub archives_dir: &'a Path,
+    pub data_output_dir: &'a Path,
+    pub config_path: &'a Path,
+    /// Directory that root-relative image URLs (`/images/a.png`) resolve
+    /// against, to record their size; `None` leaves them untouched.
+    pub static_dir: Option<&'a Path>,
+    /// Directory of the persistent build cache; `None` disables caching.
+    pub cache_dir: Option<&'a Path>,
+    /// Directory the lists of files written to each output directory are kept in.
+    pub deploy_dir: &'a Path,
+    /// Deploy into a data output directory holding files no earlier build
+    /// recorded, leaving them in place, instead of refusing to.
+    pub allow_existing_output: bool,
+    /// Abort without writing output if any post fails to parse, instead of
+    /// skipping it; enabled by either this or `[build] strict`.
+    pub strict: bool,
+    /// Include posts marked `draft: true`.
+    pub drafts: bool,
+    /// Include posts dated after the day of the build.
+    pub future: bool,
+    /// Content-hash shard file names, regardless of `[build] hashed_filenames`.
+    pub hashed_filenames: bool,
+    /// Write `.gz` and `.br` copies of every JSON file.
+    pub precompress: bool,
+    /// Encoding of the post and page shards.
+    pub format: DataFormat,
+    /// Write a static HTML page for every route into `web_root`.
+    pub prerender: bool,
+    /// Built web app (trunk output) that files served from the site root are
+    /// written into; `None` leaves it alone.
+    pub web_root: Option<&'a Path>,
+}
+
+pub fn compile(options: &CompileOptions) -> Result<()> {
+    let posts_dir = options.posts_dir;
+    let archives_dir = options.archives_dir;
+    let data_output_dir = options.data_output_dir;
+
     info!("Starting compilation...");
     info!("Posts directory: {:?}", posts_dir);
     info!("Archives directory: {:?}", archives_dir);
 
     // 1. Initialization
-    let config = load_config(config_path)?;
+    let (config, config_source) = load_config(options.config_path)?;
     info!("Configuration loaded: {:?}", config);
     let posts_per_page = config.home.posts_per_page.unwrap_or(DEFAULT_POSTS_PER_PAGE);
+    let strict = options.strict || config.build.strict;
+
+    let cache = match options.cache_dir {
+        Some(dir) => BuildCache::open(dir, &config_source)?,
+        None => BuildCache::disabled(),
+    };
 
     let temp_dir = tempfile::Builder::new()
         .prefix("sinter_build")
@@ -52,18 +310,37 @@ pub fn compile(
     let temp_path = temp_dir.path();
     info!("Temporary directory created at: {:?}", temp_path);
 
+    let highlighter = config
+        .highlight
+        .enabled
+        .then(|| Highlighter::new(&config.highlight.languages));
+
+    let assets = AssetPipeline::new(&config.assets, options.static_dir, &cache);
+
     // 2. Process Posts
-    let mut posts = load_posts_from_dir(posts_dir, "posts");
-    posts.sort_by(|a, b| {
-        let date_a = &a.0.metadata.date;
-        let date_b = &b.0.metadata.date;
-        (date_b.year, date_b.month, date_b.day).cmp(&(date_a.year, date_a.month, date_a.day))
-    });
+    let (mut posts, mut diagnostics) = load_posts_from_dir(
+        posts_dir,
+        "posts",
+        &cache,
+        highlighter.as_ref(),
+        &assets,
+        &config,
+    );
+    posts.sort_by(|a, b| newest_first(&a.0.metadata, &b.0.metadata));
     info!("Processed {} posts.", posts.len());
 
     // 3. Process Archives
     let mut archives = if archives_dir.exists() {
-        load_posts_from_dir(archives_dir, "archives")
+        let (archives, archive_diagnostics) = load_posts_from_dir(
+            archives_dir,
+            "archives",
+            &cache,
+            highlighter.as_ref(),
+            &assets,
+            &config,
+        );
+        diagnostics.extend(archive_diagnostics);
+        archives
     } else {
         info!(
             "Archives directory not found at {:?}, skipping archives.",
@@ -71,35 +348,293 @@ pub fn compile(
         );
         Vec::new()
     };
-    archives.sort_by(|a, b| {
-        let date_a = &a.0.metadata.date;
-        let date_b = &b.0.metadata.date;
-        (date_b.year, date_b.month, date_b.day).cmp(&(date_a.year, date_a.month, date_a.day))
-    });
+    archives.sort_by(|a, b| newest_first(&a.0.metadata, &b.0.metadata));
     info!("Processed {} archives.", archives.len());
+    cache.report();
+
+    let today = today();
+    let mut held_back = hold_back(&mut posts, options, &today);
+    held_back.extend(hold_back(&mut archives, options, &today));
+    if !held_back.is_empty() {
+        info!(
+            "Held back {} post(s) (use --drafts / --future to include them): {}",
+            held_back.len(),
+            held_back.join(", ")
+        );
+    }
+
+    if strict && !diagnostics.is_empty() {
+        for diagnostic in &diagnostics {
+            error!("{}", diagnostic);
+        }
+        anyhow::bail!(
+            "Strict mode: {} file(s) failed to parse, no output was written",
+            diagnostics.len()
+        );
+    }
 
     // 4. Generation
-    write_post_files(&posts, temp_path)?;
-    write_post_files(&archives, temp_path)?;
+    assign_tag_slugs(
+        posts
+            .iter_mut()
+            .chain(&mut archives)
+            .map(|(post, _)| &mut post.metadata),
+    );
+    let asset_files = assets.write(
+        posts.iter().chain(&archives).map(|(post, _)| post),
+        temp_path,
+        data_output_dir,
+    )?;
+    if asset_files > 0 {
+        info!("Published {} asset file(s).", asset_files);
+    }
+    let format = options.format;
+    write_post_files(&posts, temp_path, format)?;
+    write_post_files(&archives, temp_path, format)?;
+
+    let post_entries = site_post_entries(&posts);
+    let archive_entries = site_post_entries(&archives);
 
     // Pages for Home (Posts) -> sinter_data/pages
     let home_pages_dir = temp_path.join(PAGES_DIR);
-    generate_pages(&posts, &home_pages_dir, posts_per_page)?;
+    let home_pages = generate_pages(&post_entries, &home_pages_dir, posts_per_page, format)?;
+    info!("Generated {} pages in {:?}", home_pages, home_pages_dir);
 
     // Pages for Archives -> sinter_data/archives/pages
     let archive_pages_dir = temp_path.join("archives").join(PAGES_DIR);
-    generate_pages(&archives, &archive_pages_dir, posts_per_page)?;
+    let archive_pages =
+        generate_pages(&archive_entries, &archive_pages_dir, posts_per_page, format)?;
+    info!(
+        "Generated {} pages in {:?}",
+        archive_pages, archive_pages_dir
+    );
+
+    // Tag shards -> sinter_data/tags/<slug>/page_N.json + sinter_data/tags/index.json
+    let mut all_entries: Vec<_> = post_entries
+        .iter()
+        .chain(&archive_entries)
+        .cloned()
+        .collect();
+    all_entries.sort_by(|a, b| newest_first(&a.metadata, &b.metadata));
+    generate_tag_pages(
+        &all_entries,
+        &temp_path.join(TAGS_DIR),
+        posts_per_page,
+        format,
+    )?;
+
+    // Full-text search index -> sinter_data/search
+    if config.search.enabled {
+        let searchable: Vec<_> = posts.iter().chain(&archives).collect();
+        let (manifest, shards) =
+            search::build_index(&searchable, config.search.shards, config.search.cjk_bigrams);
+        search::write_index(&manifest, &shards, &temp_path.join(SEARCH_DIR))?;
+    }
 
-    write_site_metadata(posts.len(), &config, posts_per_page, temp_path)?;
+    // Feeds -> sinter_data/feed.xml, atom.xml, feed.json
+    let feeds_written = config.feed.enabled
+        && match &config.feed.site_url {
+            Some(site_url) => {
+                feed::write_feeds(&posts, &config.site, &config.feed, site_url, temp_path)?;
+                true
+            }
+            None => {
+                warn!("Feeds skipped: set `site_url` in the [feed] section of the config.");
+                false
+            }
+        };
+
+    // Shard renames -> sinter_data/manifest.<hash>.json
+    let manifest = if options.hashed_filenames || config.build.hashed_filenames {
+        let manifest = fingerprint::hash_shards(temp_path)?;
+        info!(
+            "Shards written under content-hashed names, see {}",
+            manifest
+        );
+        Some(manifest)
+    } else {
+        None
+    };
+
+    write_site_metadata(
+        posts.len(),
+        &config,
+        posts_per_page,
+        manifest,
+        format,
+        temp_path,
+    )?;
+
+    // Precompressed siblings -> <shard>.json.gz + <shard>.json.br
+    if options.precompress {
+        let extensions = DataFormat::ALL.map(DataFormat::extension);
+        let savings = compress::compress_dir(temp_path, &extensions)?;
+        info!("Precompressed data: {}", savings);
+    }
 
     // 5. Deployment
-    deploy_to_output(temp_path, data_output_dir)?;
+    deploy_to_output(temp_path, data_output_dir, options, false)?;
+
+    // Web root -> <web root>/sitemap.xml, robots.txt, index.html (+ posts/<slug>/index.html, ...)
+    let web_root = options.web_root.filter(|web_root| {
+        let exists = web_root.is_dir();
+        if !exists {
+            warn!(
+                "Web root {:?} not found; build the web app (`trunk build`) first. Skipping the entry page, sitemap and prerendered pages.",
+                web_root
+            );
+        }
+        exists
+    });
+    if let Some(web_root) = web_root {
+        let root_dir = tempfile::Builder::new()
+            .prefix("sinter_web_root")
+            .tempdir()
+            .context("Failed to create temporary directory")?;
+
+        // Crawlers only look for these at the root of the site.
+        if config.sitemap.enabled {
+            match config.base_url() {
+                Some(base_url) => sitemap::write_sitemap(
+                    &post_entries,
+                    &archive_entries,
+                    posts_per_page,
+                    base_url,
+                    &config.sitemap.exclude,
+                    root_dir.path(),
+                )?,
+                None => warn!(
+                    "Sitemap skipped: set `base_url` in the [sitemap] section (or `site_url` in [feed])."
+                ),
+            }
+        }
+
+        let head = if feeds_written {
+            feed::alternate_links(&config.site.title)
+        } else {
+            String::new()
+        };
+        write_html_pages(
+            &posts,
+            &archives,
+            &config,
+            posts_per_page,
+            options,
+            &head,
+            root_dir.path(),
+        )?;
+
+        // The web root also holds trunk's output, including the shell.
+        deploy_to_output(root_dir.path(), web_root, options, true)?;
+    } else if options.web_root.is_none() {
+        info!(
+            "Web root untouched: pass --web-root or --prerender to write the entry page, sitemap and robots.txt."
+        );
+    }
+
+    let pruned = cache.prune()?;
+    if pruned > 0 {
+        info!("Pruned {} stale cache entries.", pruned);
+    }
+
+    if !diagnostics.is_empty() {
+        warn!(
+            "Skipped {} file(s) that failed to parse:",
+            diagnostics.len()
+        );
+        for diagnostic in &diagnostics {
+            warn!("  {}", diagnostic);
+        }
+    }
 
     info!("Compilation finished successfully!");
     Ok(())
 }
 
-fn load_config(path: &Path) -> Result<SiteConfig> {
+/// Removes what builds wrote into the web root, putting the app shell back
+/// in place of the generated `index.html`. Returns the number of files removed.
+pub fn clean_web_root(web_root: &Path, deploy_dir: &Path) -> Result<usize> {
+    let index = web_root.join("index.html");
+    let shell = prerender::Shell::load(web_root).ok();
+    let removed = deploy::clean(web_root, deploy_dir)?;
+    if let Some(shell) = shell
+        && !index.exists()
+    {
+        fs::create_dir_all(web_root)?;
+        fs::write(&index, shell.html())
+            .with_context(|| format!("Failed to restore app shell {:?}", index))?;
+    }
+    Ok(removed)
+}
+
+/// Removes the entries of the build cache at `dir`.
+pub fn clear_cache(dir: &Path) -> Result<usize> {
+    BuildCache::clear(dir)
+}
+
+/// Client route for a post written to `path` (e.g. `archives/a.json` -> `/archives/posts/a`).
+fn post_route(path: &str) -> String {
+    let path = path.strip_suffix(".json").unwrap_or(path);
+    match path.strip_prefix("archives/") {
+        Some(slug) => format!("/archives/posts/{}", slug),
+        None => format!("/{}", path),
+    }
+}
+
+/// Removes drafts and posts dated after `today` unless `options` asks for
+/// them, returning a description of each removed post.
+fn hold_back(
+    posts: &mut Vec<(Post, String)>,
+    options: &CompileOptions,
+    today: &LiteDate,
+) -> Vec<String> {
+    let mut held_back = Vec::new();
+    posts.retain(|(post, rel_path)| {
+        let Some(reason) = held_back_reason(&post.metadata, options.drafts, options.future, today)
+        else {
+            return true;
+        };
+        held_back.push(format!("{} ({})", rel_path, reason));
+        false
+    });
+    held_back
+}
+
+/// Why a build leaves the post out, if it does: it is a draft and `drafts`
+/// is off, or it is dated after `today` and `future` is off.
+pub(crate) fn held_back_reason(
+    meta: &PostMetadata,
+    drafts: bool,
+    future: bool,
+    today: &LiteDate,
+) -> Option<String> {
+    if meta.draft && !drafts {
+        Some("draft".to_string())
+    } else if meta.date > *today && !future {
+        Some(format!("scheduled for {}", meta.date))
+    } else {
+        None
+    }
+}
+
+/// The local date of the build, which scheduled posts are compared against.
+pub(crate) fn today() -> LiteDate {
+    let today = chrono::Local::now().date_naive();
+    LiteDate {
+        year: today.year(),
+        month: today.month() as u8,
+        day: today.day() as u8,
+    }
+}
+
+fn newest_first(a: &PostMetadata, b: &PostMetadata) -> std::cmp::Ordering {
+    let (date_a, date_b) = (&a.date, &b.date);
+    (date_b.year, date_b.month, date_b.day).cmp(&(date_a.year, date_a.month, date_a.day))
+}
+
+/// Loads the site configuration, also returning the raw source so it can be
+/// used to fingerprint the build cache.
+pub(crate) fn load_config(path: &Path) -> Result<(SiteConfig, String)> {
     if !path.exists() {
         anyhow::bail!("Config file not found: {:?}", path);
     }
@@ -109,111 +644,232 @@ fn load_config(path: &Path) -> Result<SiteConfig> {
     let config: SiteConfig = toml::from_str(&content)
         .with_context(|| format!("Failed to parse config file: {:?}", path))?;
 
-    Ok(config)
+    Ok((config, content))
 }
 
-fn load_posts_from_dir(input_dir: &Path, prefix: &str) -> Vec<(Post, String)> {
-    let entries: Vec<_> = WalkDir::new(input_dir)
+/// Collects every Markdown file below `input_dir`, in a stable order.
+pub(crate) fn markdown_files(input_dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(input_dir)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
-        .collect();
-
-    info!("Found {} markdown files in {:?}.", entries.len(), input_dir);
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+        .map(|e| e.into_path())
+        .collect()
+}
 
-    entries
-        .par_iter()
-        .filter_map(|entry| {
-            let path = entry.path();
-            let relative_path = path.strip_prefix(input_dir).unwrap_or(path);
+/// Path of the JSON file generated for `path`, relative to the data output directory.
+/// A bundle's `index.md` (`my-post/index.md`) is named after its directory.
+pub(crate) fn output_rel_path(input_dir: &Path, path: &Path, prefix: &str) -> String {
+    let relative_path = path.strip_prefix(input_dir).unwrap_or(path);
 
-            // Construct the destination path for the JSON file
-            let mut dest_rel_path = PathBuf::from(prefix);
+    let mut dest_rel_path = PathBuf::from(prefix);
+    match relative_path.parent() {
+        Some(bundle)
+            if relative_path
+                .file_stem()
+                .is_some_and(|stem| stem == "index")
+                && bundle.file_name().is_some() =>
+        {
+            dest_rel_path.push(bundle);
+            dest_rel_path.as_mut_os_string().push(".json");
+        }
+        _ => {
             dest_rel_path.push(relative_path);
             dest_rel_path.set_extension("json");
+        }
+    }
 
-            let dest_path_str = dest_rel_path.to_string_lossy().replace('\\', "/");
+    dest_rel_path.to_string_lossy().replace('\\', "/")
+}
 
-            match fs::read_to_string(path) {
-                Ok(content) => match parse_post(&content) {
-                    Ok(post) => Some((post, dest_path_str)),
-                    Err(e) => {
-                        error!("Failed to parse file {:?}: {:?}", path, e);
-                        None
-                    }
-                },
-                Err(e) => {
-                    error!("Failed to read file {:?}: {:?}", path, e);
-                    None
-                }
+fn load_posts_from_dir(
+    input_dir: &Path,
+    prefix: &str,
+    cache: &BuildCache,
+    highlighter: Option<&Highlighter>,
+    assets: &AssetPipeline,
+    config: &SiteConfig,
+) -> (Vec<(Post, String)>, Vec<Diagnostic>) {
+    let entries = markdown_files(input_dir);
+
+    info!("Found {} markdown files in {:?}.", entries.len(), input_dir);
+
+    // `foo.md` and `foo/index.md` would overwrite each other's JSON file;
+    // the first one in walk order is kept and the other is reported.
+    let mut outputs: HashMap<String, &PathBuf> = HashMap::new();
+    let mut diagnostics = Vec::new();
+    let mut jobs = Vec::new();
+    for path in &entries {
+        let dest_path_str = output_rel_path(input_dir, path, prefix);
+        match outputs.get(&dest_path_str) {
+            Some(first) => diagnostics.push(Diagnostic {
+                file: path.clone(),
+                error: anyhow::anyhow!(
+                    "output `{}` is already generated from {}",
+                    dest_path_str,
+                    first.display()
+                ),
+            }),
+            None => {
+                outputs.insert(dest_path_str.clone(), path);
+                jobs.push((path, dest_path_str));
             }
+        }
+    }
+
+    let results: Vec<_> = jobs
+        .into_par_iter()
+        .map(|(path, dest_path_str)| {
+            fs::read_to_string(path)
+                .context("Failed to read file")
+                .and_then(|content| {
+                    cache.get_or_parse(&content, |content| {
+                        let mut post = parse_post(content, config)?;
+                        if let Some(highlighter) = highlighter {
+                            highlighter.highlight(&mut post.content_ast);
+                        }
+                        Ok(post)
+                    })
+                })
+                .map(|mut post| {
+                    // Done outside the cache so warnings repeat on every build,
+                    // and so asset hashes follow edits to the referenced files.
+                    for url in assets.rewrite(input_dir, path, &mut post.content_ast) {
+                        warn!("{}: asset `{}` not found", path.display(), url);
+                    }
+                    for (expression, error) in math::render_math(&mut post.content_ast) {
+                        warn!(
+                            "{}: math `{}` left as TeX: {}",
+                            path.display(),
+                            expression,
+                            error
+                        );
+                    }
+                    (post, dest_path_str)
+                })
+                .map_err(|error| {
+                    // Reported once, by the caller, once every file is loaded.
+                    Diagnostic {
+                        file: path.clone(),
+                        error,
+                    }
+                })
         })
-        .collect()
+        .collect();
+
+    let mut posts = Vec::new();
+    for result in results {
+        match result {
+            Ok(post) => posts.push(post),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+    (posts, diagnostics)
+}
+
+/// Markdown extensions enabled for every post.
+pub(crate) fn markdown_options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    options.insert(Options::ENABLE_MATH);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options
 }
 
-fn parse_post(content: &str) -> Result<Post> {
+pub(crate) fn parse_post(content: &str, config: &SiteConfig) -> Result<Post> {
     // Parse Frontmatter
     let matter = Matter::<YAML>::new();
+    let yaml = raw_frontmatter(content).unwrap_or_default();
     let result: ParsedEntity<Pod> = matter
         .parse(content)
-        .context("Failed to parse frontmatter")?;
+        .map_err(|e| FrontmatterError::explain::<PostMetadata>(yaml, e))?;
 
-    let metadata: PostMetadata = result
+    let mut metadata: PostMetadata = result
         .data
         .ok_or_else(|| anyhow::anyhow!("Missing frontmatter"))?
         .deserialize()
-        .context("Failed to deserialize frontmatter")?;
+        .map_err(|e| FrontmatterError::explain::<PostMetadata>(yaml, e))?;
 
     // Parse Markdown to AST
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
-    options.insert(Options::ENABLE_MATH);
-    options.insert(Options::ENABLE_TASKLISTS);
-
-    let parser = Parser::new_ext(&result.content, options);
-    let ast = markdown_parser::parse(parser);
+    let parser = Parser::new_ext(&result.content, markdown_options());
+    let mut ast = markdown_parser::parse(parser, &config.admonitions.kinds);
+    let toc = toc::assign_heading_ids(&mut ast);
+    let length = reading::measure(&ast);
+    metadata.word_count = length.words();
+    metadata.reading_time = length.minutes(&config.reading);
+    if metadata.summary.trim().is_empty() {
+        metadata.summary = summary::summarize(&ast, config.summary.max_chars);
+    }
 
     Ok(Post {
         metadata,
         content_ast: ast,
+        toc,
     })
 }
 
-fn write_post_files(posts: &[(Post, String)], output_dir: &Path) -> Result<()> {
+/// The YAML block between the opening and closing `---` fences, if any.
+fn raw_frontmatter(content: &str) -> Option<&str> {
+    let rest = content.trim_start_matches('\u{feff}').strip_prefix("---")?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+    let end = rest.find("\n---").map(|i| i + 1).unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+/// Writes `value` to `path` in `format`, replacing the `.json` extension of
+/// `path` with the one of the format.
+fn write_shard<T: Serialize>(value: &T, path: &Path, format: DataFormat) -> Result<()> {
+    let bytes = match format {
+        DataFormat::Json => serde_json::to_vec(value)?,
+        DataFormat::MessagePack => encoding::to_msgpack(value)?,
+    };
+    let path = path.with_extension(format.extension());
+    fs::write(&path, bytes).with_context(|| format!("Failed to write {:?}", path))
+}
+
+fn write_post_files(posts: &[(Post, String)], output_dir: &Path, format: DataFormat) -> Result<()> {
     for (post, rel_path) in posts {
         let target_path = output_dir.join(rel_path);
         if let Some(parent) = target_path.parent() {
             fs::create_dir_all(parent).context("Failed to create parent dirs for post")?;
         }
 
-        let json = serde_json::to_string(post).context("Failed to serialize post")?;
-        fs::write(&target_path, json).context("Failed to write post json")?;
+        write_shard(post, &target_path, format).context("Failed to write post")?;
     }
-    info!("Written {} individual post JSON files.", posts.len());
+    info!("Written {} individual post {} files.", posts.len(), format);
     Ok(())
 }
 
+fn site_post_entries(posts: &[(Post, String)]) -> Vec<SitePostMetadata> {
+    posts
+        .iter()
+        .map(|(post, path)| SitePostMetadata {
+            metadata: post.metadata.clone(),
+            path: path.clone(),
+        })
+        .collect()
+}
+
+/// Writes `page_N.json` files for `posts` and returns the number of pages.
 fn generate_pages(
-    posts: &[(Post, String)],
+    posts: &[SitePostMetadata],
     pages_output_dir: &Path,
     posts_per_page: usize,
-) -> Result<()> {
+    format: DataFormat,
+) -> Result<usize> {
     fs::create_dir_all(pages_output_dir).context("Failed to create pages directory")?;
 
     for (i, chunk) in posts.chunks(posts_per_page).enumerate() {
         let page_num = i + 1;
-        let mut page_posts = Vec::new();
-        let mut tags_index = HashMap::new();
-
-        for (post, path) in chunk {
-            let site_meta = SitePostMetadata {
-                metadata: post.metadata.clone(),
-                path: path.clone(),
-            };
-            page_posts.push(site_meta);
+        let mut tags_index = BTreeMap::new();
 
+        for post in chunk {
             for tag in &post.metadata.tags {
                 tags_index
                     .entry(tag.clone())
@@ -223,22 +879,129 @@ fn generate_pages(
         }
 
         let page_data = PageData {
-            posts: page_posts,
+            posts: chunk.to_vec(),
             tags_index,
         };
 
-        let page_json =
-            serde_json::to_string(&page_data).context("Failed to serialize page data")?;
-        fs::write(
-            pages_output_dir.join(format!("page_{}.json", page_num)),
-            page_json,
+        write_shard(
+            &page_data,
+            &pages_output_dir.join(format!("page_{}.json", page_num)),
+            format,
         )
-        .context("Failed to write page json")?;
+        .context("Failed to write page data")?;
+    }
+
+    Ok(posts.len().div_ceil(posts_per_page))
+}
+
+/// Fills in `tag_slugs` of every post. Tags that only differ in case share a
+/// slug; distinct tags whose [`tag_slug`]s clash (`C++` and `C plus plus`) get
+/// `-2`, `-3`, ... in alphabetical order, so a slug does not depend on which
+/// posts happen to use the tag first.
+fn assign_tag_slugs<'a>(posts: impl Iterator<Item = &'a mut PostMetadata>) {
+    let posts: Vec<_> = posts.collect();
+    let mut by_slug: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for tag in posts.iter().flat_map(|meta| &meta.tags) {
+        let key = tag.trim().to_lowercase();
+        if !key.is_empty() {
+            by_slug.entry(tag_slug(&key)).or_default().insert(key);
+        }
+    }
+
+    // Unsuffixed slugs first, so a suffix never takes another tag's own slug.
+    let mut taken: BTreeSet<String> = by_slug.keys().cloned().collect();
+    let mut slugs = BTreeMap::new();
+    for (slug, keys) in by_slug {
+        let mut keys = keys.into_iter();
+        if let Some(first) = keys.next() {
+            slugs.insert(first, slug.clone());
+        }
+        let mut n = 2;
+        for key in keys {
+            while taken.contains(&format!("{}-{}", slug, n)) {
+                n += 1;
+            }
+            let suffixed = format!("{}-{}", slug, n);
+            taken.insert(suffixed.clone());
+            slugs.insert(key, suffixed);
+        }
+    }
+
+    for meta in posts {
+        meta.tag_slugs = meta
+            .tags
+            .iter()
+            .map(|tag| {
+                slugs
+                    .get(&tag.trim().to_lowercase())
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+    }
+}
+
+/// Groups `posts` (already in display order) by tag slug. Tags sharing a slug
+/// only differ in case and are merged under the first name seen.
+fn group_by_tag(posts: &[SitePostMetadata]) -> BTreeMap<String, (String, Vec<SitePostMetadata>)> {
+    let mut groups: BTreeMap<String, (String, Vec<SitePostMetadata>)> = BTreeMap::new();
+    for post in posts {
+        let mut seen = Vec::new();
+        for (tag, slug) in post.metadata.tags_with_slugs() {
+            let name = tag.trim();
+            if name.is_empty() {
+                continue;
+            }
+            if seen.contains(&slug) {
+                continue;
+            }
+            let (_, tagged) = groups
+                .entry(slug.clone())
+                .or_insert_with(|| (name.to_string(), Vec::new()));
+            tagged.push(post.clone());
+            seen.push(slug);
+        }
     }
+    groups
+}
 
-    let total_pages = (posts.len() + posts_per_page - 1) / posts_per_page;
-    info!("Generated {} pages in {:?}", total_pages, pages_output_dir);
+/// Writes one paginated shard per tag plus the global tag index.
+fn generate_tag_pages(
+    posts: &[SitePostMetadata],
+    tags_output_dir: &Path,
+    posts_per_page: usize,
+    format: DataFormat,
+) -> Result<()> {
+    fs::create_dir_all(tags_output_dir).context("Failed to create tags directory")?;
+
+    let mut tags = Vec::new();
+    for (slug, (name, tagged)) in group_by_tag(posts) {
+        let total_pages = generate_pages(
+            &tagged,
+            &tags_output_dir.join(&slug),
+            posts_per_page,
+            format,
+        )?;
+        tags.push(TagSummary {
+            name,
+            slug,
+            count: tagged.len(),
+            total_pages,
+        });
+    }
+    // Most used first; ties keep a stable alphabetical order.
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
 
+    let index_json = serde_json::to_string(&TagIndex { tags: tags.clone() })
+        .context("Failed to serialize tag index")?;
+    fs::write(tags_output_dir.join(TAGS_INDEX_FILENAME), index_json)
+        .context("Failed to write tag index")?;
+
+    info!(
+        "Generated {} tag shards in {:?}",
+        tags.len(),
+        tags_output_dir
+    );
     Ok(())
 }
 
@@ -246,6 +1009,8 @@ fn write_site_metadata(
     total_posts: usize,
     config: &SiteConfig,
     posts_per_page: usize,
+    manifest: Option<String>,
+    format: DataFormat,
     output_dir: &Path,
 ) -> Result<()> {
     let total_pages = if total_posts == 0 {
@@ -260,8 +1025,11 @@ fn write_site_metadata(
         subtitle: config.site.subtitle.clone(),
         description: config.site.description.clone(),
         total_pages,
+        manifest,
+        format,
     };
 
+    // Always JSON: the client reads the shard format from this file.
     let output_path = output_dir.join(SITE_DATA_FILENAME);
     let json = serde_json::to_string(&site_meta).context("Failed to serialize site metadata")?;
     fs::write(&output_path, json).context("Failed to write site metadata file")?;
@@ -270,44 +1038,80 @@ fn write_site_metadata(
     Ok(())
 }
 
-fn deploy_to_output(temp_path: &Path, output_dir: &Path) -> Result<()> {
-    if !output_dir.exists() {
-        fs::create_dir_all(output_dir).context("Failed to create final output directory")?;
-    }
-
-    // Helper for recursive copy
-    fn copy_recursive(src: &Path, dst: &Path) -> Result<()> {
-        for entry in WalkDir::new(src) {
-            let entry = entry?;
-            let path = entry.path();
-            if path == src {
-                continue;
-            }
-
-            let rel_path = path.strip_prefix(src)?;
-            let target = dst.join(rel_path);
-
-            if path.is_dir() {
-                fs::create_dir_all(&target)?;
-            } else {
-                if let Some(parent) = target.parent() {
-                    fs::create_dir_all(parent)?;
-                }
-                fs::copy(path, &target)?;
-            }
+/// Writes the HTML pages for the web root into `output_dir`, built from the
+/// shell in `options.web_root` with `head` added to every one of them: the
+/// app entry point, or with `options.prerender` a static page for every route.
+fn write_html_pages(
+    posts: &[(Post, String)],
+    archives: &[(Post, String)],
+    config: &SiteConfig,
+    posts_per_page: usize,
+    options: &CompileOptions,
+    head: &str,
+    output_dir: &Path,
+) -> Result<()> {
+    let Some(web_root) = options.web_root else {
+        return Ok(());
+    };
+    let shell = match prerender::Shell::load(web_root) {
+        Ok(shell) => shell,
+        Err(e) if !options.prerender => {
+            warn!("App entry point skipped: {:#}", e);
+            return Ok(());
         }
-        Ok(())
+        Err(e) => return Err(e),
+    };
+    if options.prerender {
+        prerender::write_pages(
+            posts,
+            archives,
+            config,
+            posts_per_page,
+            head,
+            &shell,
+            output_dir,
+        )?;
+    } else {
+        prerender::write_index(config, head, &shell, output_dir)?;
     }
+    Ok(())
+}
 
-    copy_recursive(temp_path, output_dir)?;
+fn deploy_to_output(
+    temp_path: &Path,
+    output_dir: &Path,
+    options: &CompileOptions,
+    shared: bool,
+) -> Result<()> {
+    let report = deploy::deploy(
+        temp_path,
+        output_dir,
+        options.deploy_dir,
+        shared,
+        options.allow_existing_output,
+    )?;
     info!(
-        "Content deployed from temporary directory to {:?}",
-        output_dir
+        "Content deployed from temporary directory to {:?} ({} written, {} unchanged, {} stale removed)",
+        output_dir, report.written, report.unchanged, report.removed
     );
     Ok(())
 }
 
+mod assets;
+mod cache;
+mod diagnostics;
+mod feed;
+mod fingerprint;
+mod highlight;
+mod html;
 mod markdown_parser;
+mod math;
+mod prerender;
+mod reading;
+mod search;
+mod sitemap;
+mod summary;
+mod toc;
 
 #[cfg(test)]
 mod tests {
@@ -328,10 +1132,12 @@ summary: "A summary"
 # Hello World
 This is a test."#;
 
-        let post = parse_post(content).expect("Failed to parse post");
+        let post = parse_post(content, &SiteConfig::default()).expect("Failed to parse post");
 
         assert_eq!(post.metadata.title, "Test Post");
         assert_eq!(post.metadata.slug, "test-post");
+        assert_eq!(post.metadata.word_count, 6);
+        assert_eq!(post.metadata.reading_time, 1);
         // Verify AST structure
         // Root -> [Heading, Paragraph]
         assert!(matches!(
@@ -341,10 +1147,229 @@ This is a test."#;
         assert!(matches!(post.content_ast[1], ContentNode::Paragraph { .. }));
     }
 
+    #[test]
+    fn test_parse_post_without_summary() {
+        let content = "---\nid: \"1\"\ntitle: \"T\"\nslug: \"t\"\ndate: \"2023-01-01\"\n---\n# Title\nFirst *paragraph*.\n\n<!-- more -->\n\nRest.\n";
+
+        let post = parse_post(content, &SiteConfig::default()).expect("Failed to parse post");
+        assert_eq!(post.metadata.summary, "First paragraph.");
+    }
+
+    #[test]
+    fn test_output_rel_path() {
+        let dir = Path::new("posts");
+        let rel = |path: &str| output_rel_path(dir, &dir.join(path), "posts");
+        assert_eq!(rel("hello.md"), "posts/hello.json");
+        assert_eq!(rel("2024/hello.md"), "posts/2024/hello.json");
+        assert_eq!(rel("my.post/index.md"), "posts/my.post.json");
+        assert_eq!(rel("index.md"), "posts/index.json");
+    }
+
+    #[test]
+    fn test_clean_web_root_restores_shell() {
+        let root = tempfile::tempdir().unwrap();
+        let web_root = root.path().join("dist");
+        let lists = root.path().join("deploy");
+        fs::create_dir_all(&web_root).unwrap();
+        let shell = "<html><head></head><body><script src=\"app.js\"></script></body></html>";
+        fs::write(web_root.join("index.html"), shell).unwrap();
+        fs::write(web_root.join("app.js"), "").unwrap();
+
+        let build = tempfile::tempdir().unwrap();
+        let page = shell.replace(
+            "</head>",
+            "<!--sinter:head--><title>Site</title><!--/sinter:head--></head>",
+        );
+        fs::write(build.path().join("index.html"), page).unwrap();
+        fs::write(build.path().join("robots.txt"), "").unwrap();
+        deploy::deploy(build.path(), &web_root, &lists, true, false).unwrap();
+
+        assert_eq!(clean_web_root(&web_root, &lists).unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(web_root.join("index.html")).unwrap(),
+            shell
+        );
+        assert!(web_root.join("app.js").exists());
+        assert!(!web_root.join("robots.txt").exists());
+    }
+
+    #[test]
+    fn test_load_posts_reports_output_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let post = "---\nid: \"1\"\ntitle: \"T\"\nslug: \"foo\"\ndate: \"2024-01-01\"\n---\nBody\n";
+        fs::create_dir_all(dir.path().join("foo")).unwrap();
+        fs::write(dir.path().join("foo/index.md"), post).unwrap();
+        fs::write(dir.path().join("foo.md"), post).unwrap();
+
+        let config = SiteConfig::default();
+        let (posts, diagnostics) = load_posts_from_dir(
+            dir.path(),
+            "posts",
+            &BuildCache::disabled(),
+            None,
+            &AssetPipeline::new(&config.assets, None, &BuildCache::disabled()),
+            &config,
+        );
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].1, "posts/foo.json");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, dir.path().join("foo.md"));
+        assert!(
+            diagnostics[0]
+                .error
+                .to_string()
+                .starts_with("output `posts/foo.json` is already generated from")
+        );
+    }
+
+    #[test]
+    fn test_hold_back_drafts_and_future_posts() {
+        let post = |slug: &str, date: &str, draft: bool| {
+            let content = format!(
+                "---\nid: \"{0}\"\ntitle: \"T\"\nslug: \"{0}\"\ndate: \"{1}\"\ndraft: {2}\n---\nBody\n",
+                slug, date, draft
+            );
+            let post = parse_post(&content, &SiteConfig::default()).unwrap();
+            (post, format!("posts/{}.json", slug))
+        };
+        let posts = vec![
+            post("old", "2024-01-01", false),
+            post("draft", "2024-01-01", true),
+            post("today", "2024-06-01", false),
+            post("later", "2024-06-02", false),
+        ];
+        let today = LiteDate {
+            year: 2024,
+            month: 6,
+            day: 1,
+        };
+        let mut options = CompileOptions {
+            posts_dir: Path::new("posts"),
+            archives_dir: Path::new("archives"),
+            data_output_dir: Path::new("out"),
+            config_path: Path::new("sinter.toml"),
+            static_dir: None,
+            cache_dir: None,
+            deploy_dir: Path::new(".sinter/deploy"),
+            allow_existing_output: false,
+            strict: false,
+            drafts: false,
+            future: false,
+            hashed_filenames: false,
+            precompress: false,
+            format: DataFormat::Json,
+            prerender: false,
+            web_root: None,
+        };
+
+        let mut kept = posts.clone();
+        let held_back = hold_back(&mut kept, &options, &today);
+        assert_eq!(
+            held_back,
+            [
+                "posts/draft.json (draft)",
+                "posts/later.json (scheduled for 2024-06-02)"
+            ]
+        );
+        let slugs: Vec<_> = kept.iter().map(|(p, _)| p.metadata.slug.as_str()).collect();
+        assert_eq!(slugs, ["old", "today"]);
+
+        options.drafts = true;
+        options.future = true;
+        let mut kept = posts.clone();
+        assert!(hold_back(&mut kept, &options, &today).is_empty());
+        assert_eq!(kept.len(), 4);
+    }
+
+    #[test]
+    fn test_generate_tag_pages_merges_and_paginates() {
+        let entry = |slug: &str, tags: &[&str]| SitePostMetadata {
+            metadata: PostMetadata {
+                id: slug.to_string(),
+                title: slug.to_string(),
+                slug: slug.to_string(),
+                date: sinter_core::LiteDate {
+                    year: 2024,
+                    month: 1,
+                    day: 1,
+                },
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                tag_slugs: vec![],
+                draft: false,
+                summary: String::new(),
+                word_count: 0,
+                reading_time: 0,
+            },
+            path: format!("posts/{}.json", slug),
+        };
+        let posts = vec![
+            entry("a", &["Rust", "rust"]),
+            entry("b", &["rust", "Web"]),
+            entry("c", &["Rust"]),
+        ];
+
+        let dir = tempfile::tempdir().unwrap();
+        generate_tag_pages(&posts, dir.path(), 2, DataFormat::Json).unwrap();
+
+        let index: TagIndex =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("index.json")).unwrap())
+                .unwrap();
+        let summary: Vec<_> = index
+            .tags
+            .iter()
+            .map(|t| (t.name.as_str(), t.slug.as_str(), t.count, t.total_pages))
+            .collect();
+        assert_eq!(summary, [("Rust", "rust", 3, 2), ("Web", "web", 1, 1)]);
+
+        let page: PageData =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("rust/page_2.json")).unwrap())
+                .unwrap();
+        assert_eq!(page.posts.len(), 1);
+        assert_eq!(page.posts[0].metadata.slug, "c");
+    }
+
+    #[test]
+    fn test_assign_tag_slugs_keeps_distinct_tags_apart() {
+        let mut metadata: Vec<PostMetadata> = [
+            &["C++", "Rust"][..],
+            &["C", "c plus plus", "rust"],
+            &["C#", "c-plus-plus-2"],
+        ]
+        .iter()
+        .map(|tags| PostMetadata {
+            id: String::new(),
+            title: String::new(),
+            slug: String::new(),
+            date: sinter_core::LiteDate {
+                year: 2024,
+                month: 1,
+                day: 1,
+            },
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            tag_slugs: vec![],
+            draft: false,
+            summary: String::new(),
+            word_count: 0,
+            reading_time: 0,
+        })
+        .collect();
+        assign_tag_slugs(metadata.iter_mut());
+
+        let slugs: Vec<_> = metadata.iter().map(|m| m.tag_slugs.clone()).collect();
+        assert_eq!(
+            slugs,
+            [
+                vec!["c-plus-plus-3", "rust"],
+                vec!["c", "c-plus-plus", "rust"],
+                vec!["c-sharp", "c-plus-plus-2"],
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_post_missing_frontmatter() {
         let content = "# Just Markdown";
-        let result = parse_post(content);
+        let result = parse_post(content, &SiteConfig::default());
         assert!(result.is_err());
     }
 }
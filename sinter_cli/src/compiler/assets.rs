@@ -0,0 +1,669 @@
+//! Files referenced from posts by relative URL, e.g. images next to the
+//! Markdown file in a `posts/my-post/index.md` bundle.
+//!
+//! Each file is published under `assets/` with a content hash in its name, so
+//! it can be cached forever, and the post's URL is rewritten to point at it.
+//! Images also get their intrinsic size and a tiny blurred preview recorded on
+//! the node, and raster images can be resized to the configured widths for
+//! `srcset`. Root-relative images (`/images/a.png`) are looked up in the static
+//! directory and get the same size and preview, but keep their URL.
+//!
+//! Decoding is the slow part, so sizes and previews are kept in the build cache
+//! under the file's content hash.
+
+use super::cache::BuildCache;
+use super::{AssetsSection, DATA_URL_PATH};
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageReader};
+use percent_encoding::percent_decode_str;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sinter_core::{ContentNode, ImageSource, Post, slugify};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Directory, relative to the data output, that assets are written to.
+const ASSETS_DIR: &str = "assets";
+
+/// Longest side, in pixels, of the blurred preview embedded in the AST.
+const PLACEHOLDER_SIZE: u32 = 16;
+
+/// An asset to publish: the source file and the widths to resize it to.
+#[derive(Debug, Clone)]
+struct Asset {
+    source: PathBuf,
+    widths: Vec<u32>,
+    /// Set when the file is an image that could be decoded.
+    image: Option<ImageInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ImageInfo {
+    width: u32,
+    height: u32,
+    placeholder: Option<String>,
+}
+
+/// Where a referenced file ended up.
+struct Published {
+    url: String,
+    srcset: Vec<ImageSource>,
+    image: Option<ImageInfo>,
+}
+
+pub struct AssetPipeline<'a> {
+    config: &'a AssetsSection,
+    /// Directory that root-relative image URLs resolve against.
+    static_dir: Option<&'a Path>,
+    cache: &'a BuildCache,
+    /// Output file name -> asset, shared by every post that references it.
+    assets: Mutex<BTreeMap<String, Asset>>,
+}
+
+impl<'a> AssetPipeline<'a> {
+    pub fn new(
+        config: &'a AssetsSection,
+        static_dir: Option<&'a Path>,
+        cache: &'a BuildCache,
+    ) -> Self {
+        Self {
+            config,
+            static_dir,
+            cache,
+            assets: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Points local image URLs, and links to files other than posts, at their
+    /// published copy. `post_path` is the Markdown file the nodes come from,
+    /// inside `content_dir`; files outside `content_dir` are never published.
+    ///
+    /// Returns the URLs that did not resolve to a file.
+    pub fn rewrite(
+        &self,
+        content_dir: &Path,
+        post_path: &Path,
+        nodes: &mut [ContentNode],
+    ) -> Vec<String> {
+        let base = post_path.parent().unwrap_or_else(|| Path::new("."));
+        let mut missing = Vec::new();
+        self.rewrite_nodes(&normalize(content_dir), base, nodes, &mut missing);
+        missing
+    }
+
+    fn rewrite_nodes(
+        &self,
+        root: &Path,
+        base: &Path,
+        nodes: &mut [ContentNode],
+        missing: &mut Vec<String>,
+    ) {
+        for node in nodes {
+            match node {
+                ContentNode::Image {
+                    url,
+                    srcset,
+                    width,
+                    height,
+                    placeholder,
+                    ..
+                } if is_relative_url(url) => match self.publish(root, base, url) {
+                    Ok(Some(published)) => {
+                        *url = published.url;
+                        *srcset = published.srcset;
+                        if let Some(info) = published.image {
+                            *width = Some(info.width);
+                            *height = Some(info.height);
+                            *placeholder = info.placeholder;
+                        }
+                    }
+                    _ => missing.push(url.clone()),
+                },
+                ContentNode::Image {
+                    url,
+                    width,
+                    height,
+                    placeholder,
+                    ..
+                } if is_root_relative_url(url) => {
+                    if let Some(info) = self.describe_static(url) {
+                        *width = Some(info.width);
+                        *height = Some(info.height);
+                        *placeholder = info.placeholder;
+                    }
+                }
+                ContentNode::Link { url, children, .. } => {
+                    // Only links to files are assets: extensionless ones (`../about`)
+                    // are site routes, `.md` ones point at other posts and
+                    // `rust-lang.org` is a domain missing its scheme.
+                    if is_relative_url(url) && is_asset_link(url) {
+                        match self.publish(root, base, url) {
+                            Ok(Some(published)) => *url = published.url,
+                            _ => missing.push(url.clone()),
+                        }
+                    }
+                    self.rewrite_nodes(root, base, children, missing);
+                }
+                _ => {
+                    if let Some(children) = children_mut(node) {
+                        self.rewrite_nodes(root, base, children, missing);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Registers the file `url` points to and returns where it is published,
+    /// or `None` if it is missing or lies outside `root`.
+    fn publish(&self, root: &Path, base: &Path, url: &str) -> Result<Option<Published>> {
+        let path = strip_query(url);
+        let suffix = &url[path.len()..];
+        let source = normalize(&base.join(percent_decode_str(path).decode_utf8_lossy().as_ref()));
+        if !source.starts_with(root) || !source.is_file() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&source).with_context(|| format!("Failed to read {:?}", source))?;
+        let hash = blake3::hash(&bytes).to_hex();
+        let stem = source
+            .file_stem()
+            .map(|s| slugify(&s.to_string_lossy()))
+            .unwrap_or_default();
+        let extension = source
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let name = |width: Option<u32>| {
+            let mut name = format!("{}.{}", stem, &hash[..10]);
+            if let Some(width) = width {
+                name.push_str(&format!("-{}w", width));
+            }
+            if !extension.is_empty() {
+                name.push('.');
+                name.push_str(&extension);
+            }
+            name
+        };
+        let public_url = |name: &str| format!("{}/{}/{}", DATA_URL_PATH, ASSETS_DIR, name);
+        let file_name = name(None);
+
+        // Files shared by several posts are only inspected once.
+        let known = self.assets.lock().unwrap().get(&file_name).cloned();
+        let asset = match known {
+            Some(asset) => asset,
+            None => {
+                let asset = self.inspect(source, &bytes, &extension, &hash);
+                self.assets
+                    .lock()
+                    .unwrap()
+                    .insert(file_name.clone(), asset.clone());
+                asset
+            }
+        };
+
+        let mut srcset = Vec::new();
+        if let (false, Some(info)) = (asset.widths.is_empty(), &asset.image) {
+            srcset = asset
+                .widths
+                .iter()
+                .map(|&width| ImageSource {
+                    url: public_url(&name(Some(width))),
+                    width,
+                })
+                .collect();
+            srcset.push(ImageSource {
+                url: public_url(&file_name),
+                width: info.width,
+            });
+        }
+
+        Ok(Some(Published {
+            url: format!("{}{}", public_url(&file_name), suffix),
+            srcset,
+            image: asset.image,
+        }))
+    }
+
+    /// Reads the size of an image, renders its placeholder and picks the
+    /// widths to resize it to. Other files are copied as they are.
+    fn inspect(&self, source: PathBuf, bytes: &[u8], extension: &str, hash: &str) -> Asset {
+        let Some(info) = self.image_info(&source, bytes, extension, hash) else {
+            return Asset {
+                source,
+                widths: Vec::new(),
+                image: None,
+            };
+        };
+
+        // Only formats that can be re-encoded are resized, and never upscaled.
+        let mut widths = Vec::new();
+        if resizable_format(extension).is_some() {
+            widths = self.config.responsive_widths.clone();
+            widths.retain(|&w| w > 0 && w < info.width);
+            widths.sort_unstable();
+            widths.dedup();
+        }
+
+        Asset {
+            source,
+            widths,
+            image: Some(info),
+        }
+    }
+
+    /// Size and placeholder of the image at the root-relative `url`, if it is
+    /// a readable image in the static directory.
+    fn describe_static(&self, url: &str) -> Option<ImageInfo> {
+        let path = percent_decode_str(strip_query(url)).decode_utf8_lossy();
+        let static_dir = normalize(self.static_dir?);
+        let source = normalize(&static_dir.join(path.trim_start_matches('/')));
+        if !source.starts_with(&static_dir) {
+            return None;
+        }
+        let bytes = fs::read(&source).ok()?;
+        let extension = source
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let hash = blake3::hash(&bytes).to_hex();
+        self.image_info(&source, &bytes, &extension, &hash)
+    }
+
+    /// Decodes the size, and the placeholder if enabled, of an image; `None`
+    /// for files that are not images. Results are cached by content hash.
+    fn image_info(
+        &self,
+        source: &Path,
+        bytes: &[u8],
+        extension: &str,
+        hash: &str,
+    ) -> Option<ImageInfo> {
+        let format = ImageFormat::from_extension(extension).filter(|f| f.reading_enabled())?;
+
+        // The cache is salted with the configuration, so toggling
+        // placeholders does not reuse stale entries.
+        let key = format!("image:{}.{}", hash, extension);
+        if let Some(info) = self.cache.get(&key) {
+            return Some(info);
+        }
+
+        let (size, placeholder) = if self.config.placeholders {
+            match image::load_from_memory_with_format(bytes, format) {
+                Ok(image) => (Some(image.dimensions()), lqip(&image)),
+                Err(_) => (None, None),
+            }
+        } else {
+            let reader = ImageReader::with_format(Cursor::new(bytes), format);
+            (reader.into_dimensions().ok(), None)
+        };
+        let Some((width, height)) = size else {
+            warn!("{}: not a readable {:?} image", source.display(), format);
+            return None;
+        };
+
+        let info = ImageInfo {
+            width,
+            height,
+            placeholder,
+        };
+        self.cache.put(&key, &info);
+        Some(info)
+    }
+
+    /// Writes every asset still referenced by `posts` (drafts that were held
+    /// back are not) into `output_dir/assets`. Returns the number of files.
+    ///
+    /// Resized variants an earlier build left in `published_dir/assets` are
+    /// copied instead of being decoded and resized again.
+    pub fn write<'p>(
+        &self,
+        posts: impl IntoIterator<Item = &'p Post>,
+        output_dir: &Path,
+        published_dir: &Path,
+    ) -> Result<usize> {
+        let prefix = format!("{}/{}/", DATA_URL_PATH, ASSETS_DIR);
+        let mut referenced = HashSet::new();
+        for post in posts {
+            collect_asset_names(&post.content_ast, &prefix, &mut referenced);
+        }
+
+        let assets = self.assets.lock().unwrap();
+        let pending: Vec<_> = assets
+            .iter()
+            .filter(|(name, _)| referenced.contains(name.as_str()))
+            .collect();
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let dir = output_dir.join(ASSETS_DIR);
+        let published = published_dir.join(ASSETS_DIR);
+        fs::create_dir_all(&dir).context("Failed to create assets directory")?;
+        let written = pending
+            .par_iter()
+            .map(|(name, asset)| write_asset(name, asset, &dir, &published))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(written.into_iter().sum())
+    }
+}
+
+/// Copies `asset` to `dir/name` and writes its resized variants, returning
+/// the number of files written. Variants found in `published` are reused.
+fn write_asset(name: &str, asset: &Asset, dir: &Path, published: &Path) -> Result<usize> {
+    fs::copy(&asset.source, dir.join(name))
+        .with_context(|| format!("Failed to copy asset {:?}", asset.source))?;
+    if asset.widths.is_empty() {
+        return Ok(1);
+    }
+
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    let variant = |width: u32| format!("{}-{}w.{}", stem, width, extension);
+    // The name carries the source's hash, so a published variant is still current.
+    let mut resize = Vec::new();
+    for &width in &asset.widths {
+        if fs::copy(published.join(variant(width)), dir.join(variant(width))).is_err() {
+            resize.push(width);
+        }
+    }
+    if resize.is_empty() {
+        return Ok(1 + asset.widths.len());
+    }
+
+    let format = resizable_format(extension).context("Unsupported image format")?;
+    let image = image::open(&asset.source)
+        .with_context(|| format!("Failed to decode image {:?}", asset.source))?;
+    for width in resize {
+        let height = (image.height() as u64 * width as u64 / image.width() as u64).max(1) as u32;
+        let path = dir.join(variant(width));
+        image
+            .resize_exact(width, height, FilterType::Lanczos3)
+            .save_with_format(&path, format)
+            .with_context(|| format!("Failed to write {:?}", path))?;
+    }
+    Ok(1 + asset.widths.len())
+}
+
+fn collect_asset_names<'n>(nodes: &'n [ContentNode], prefix: &str, out: &mut HashSet<&'n str>) {
+    for node in nodes {
+        match node {
+            ContentNode::Image { url, .. } => {
+                if let Some(name) = url.strip_prefix(prefix) {
+                    out.insert(strip_query(name));
+                }
+            }
+            ContentNode::Link { url, children, .. } => {
+                if let Some(name) = url.strip_prefix(prefix) {
+                    out.insert(strip_query(name));
+                }
+                collect_asset_names(children, prefix, out);
+            }
+            _ => {
+                if let Some(children) = children(node) {
+                    collect_asset_names(children, prefix, out);
+                }
+            }
+        }
+    }
+}
+
+/// A tiny PNG of `image` as a data URI, for themes to show blurred while the
+/// real image loads.
+fn lqip(image: &DynamicImage) -> Option<String> {
+    let mut png = Vec::new();
+    image
+        .thumbnail(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .ok()?;
+    Some(format!("data:image/png;base64,{}", STANDARD.encode(png)))
+}
+
+fn resizable_format(extension: &str) -> Option<ImageFormat> {
+    match extension {
+        "png" => Some(ImageFormat::Png),
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        _ => None,
+    }
+}
+
+fn children(node: &ContentNode) -> Option<&Vec<ContentNode>> {
+    match node {
+        ContentNode::Paragraph { children }
+        | ContentNode::Heading { children, .. }
+        | ContentNode::List { children, .. }
+        | ContentNode::ListItem { children }
+        | ContentNode::BlockQuote { children }
+        | ContentNode::Admonition { children, .. }
+        | ContentNode::FootnoteDefinition { children, .. }
+        | ContentNode::Emphasis { children }
+        | ContentNode::Strong { children }
+        | ContentNode::Strikethrough { children }
+        | ContentNode::Link { children, .. }
+        | ContentNode::Table { children, .. }
+        | ContentNode::TableHead { children }
+        | ContentNode::TableBody { children }
+        | ContentNode::TableRow { children }
+        | ContentNode::TableCell { children, .. } => Some(children),
+        _ => None,
+    }
+}
+
+fn children_mut(node: &mut ContentNode) -> Option<&mut Vec<ContentNode>> {
+    match node {
+        ContentNode::Paragraph { children }
+        | ContentNode::Heading { children, .. }
+        | ContentNode::List { children, .. }
+        | ContentNode::ListItem { children }
+        | ContentNode::BlockQuote { children }
+        | ContentNode::Admonition { children, .. }
+        | ContentNode::FootnoteDefinition { children, .. }
+        | ContentNode::Emphasis { children }
+        | ContentNode::Strong { children }
+        | ContentNode::Strikethrough { children }
+        | ContentNode::Link { children, .. }
+        | ContentNode::Table { children, .. }
+        | ContentNode::TableHead { children }
+        | ContentNode::TableBody { children }
+        | ContentNode::TableRow { children }
+        | ContentNode::TableCell { children, .. } => Some(children),
+        _ => None,
+    }
+}
+
+/// Whether `url` is a path relative to the post, as opposed to an absolute,
+/// root-relative or in-page URL.
+fn is_relative_url(url: &str) -> bool {
+    is_local_url(url) && !url.starts_with('/') && !url.starts_with('#') && !url.contains(':')
+}
+
+/// Whether `url` is a path from the site root, like `/images/a.png`.
+fn is_root_relative_url(url: &str) -> bool {
+    is_local_url(url) && url.starts_with('/')
+}
+
+/// Extensions of the files a relative link is published as an asset for.
+/// Anything else, `.html` and top-level domains included, is left alone.
+const ASSET_EXTENSIONS: &[&str] = &[
+    "7z", "avif", "bmp", "csv", "epub", "gif", "gz", "ico", "jpeg", "jpg", "json", "m4a", "mov",
+    "mp3", "mp4", "ogg", "opus", "pdf", "png", "svg", "tar", "tgz", "txt", "wav", "webm", "webp",
+    "xz", "zip",
+];
+
+/// Whether the relative link `url` names a downloadable file by its extension.
+fn is_asset_link(url: &str) -> bool {
+    Path::new(strip_query(url))
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| ASSET_EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// `path` with `.` and `..` resolved lexically, so symlinked content still
+/// counts as inside its directory.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) {
+                    out.pop();
+                } else {
+                    out.push("..");
+                }
+            }
+            component => out.push(component),
+        }
+    }
+    out
+}
+
+/// Whether `url` points at a file of this site rather than another origin.
+pub(crate) fn is_local_url(url: &str) -> bool {
+    !url.is_empty() && !url.contains("://") && !url.starts_with("//") && !url.starts_with("data:")
+}
+
+/// `url` without its query string and fragment.
+pub(crate) fn strip_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{SiteConfig, parse_post};
+
+    #[test]
+    fn test_rewrite_and_write_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let content_dir = dir.path().join("posts");
+        let bundle = content_dir.join("my-post");
+        fs::create_dir_all(&bundle).unwrap();
+        image::RgbImage::new(40, 20)
+            .save(bundle.join("Cover Photo.png"))
+            .unwrap();
+        fs::write(bundle.join("slides.pdf"), b"%PDF").unwrap();
+        fs::write(dir.path().join("secret.pdf"), b"%PDF").unwrap();
+        let static_dir = dir.path().join("static");
+        fs::create_dir_all(static_dir.join("images")).unwrap();
+        image::RgbImage::new(30, 10)
+            .save(static_dir.join("images/a.png"))
+            .unwrap();
+        fs::write(static_dir.join("images/cached.png"), b"not a png").unwrap();
+
+        let mut post = parse_post(
+            "---\nid: \"1\"\ntitle: \"T\"\nslug: \"my-post\"\ndate: \"2024-01-01\"\n---\n![cover](Cover%20Photo.png) [slides](./slides.pdf#page=2) [next](other.md) [about](../about) ![gone](gone.png) ![abs](/images/a.png) ![cached](/images/cached.png) [rust](rust-lang.org) [page](example.com/page.html) [secret](../../secret.pdf)\n",
+            &SiteConfig::default(),
+        )
+        .unwrap();
+        let config = AssetsSection {
+            responsive_widths: vec![10, 80, 20],
+            placeholders: true,
+        };
+        // Decoding results come from the cache when it has them.
+        let cache = BuildCache::open(&dir.path().join("cache"), "config").unwrap();
+        let cached = ImageInfo {
+            width: 7,
+            height: 3,
+            placeholder: None,
+        };
+        let hash = blake3::hash(b"not a png").to_hex();
+        cache.put(&format!("image:{}.png", hash), &cached);
+
+        let pipeline = AssetPipeline::new(&config, Some(&static_dir), &cache);
+        let missing = pipeline.rewrite(
+            &content_dir,
+            &bundle.join("index.md"),
+            &mut post.content_ast,
+        );
+        // Files outside the content directory are never published.
+        assert_eq!(missing, ["gone.png", "../../secret.pdf"]);
+
+        let ContentNode::Paragraph { children } = &post.content_ast[0] else {
+            panic!("Expected Paragraph");
+        };
+        let ContentNode::Image {
+            url,
+            srcset,
+            width,
+            height,
+            placeholder,
+            ..
+        } = &children[0]
+        else {
+            panic!("Expected Image");
+        };
+        assert_eq!((*width, *height), (Some(40), Some(20)));
+        assert!(
+            placeholder
+                .as_deref()
+                .is_some_and(|p| p.starts_with("data:image/png;base64,"))
+        );
+        assert!(url.starts_with("/sinter_data/assets/cover-photo."));
+        assert!(url.ends_with(".png"));
+        let widths: Vec<u32> = srcset.iter().map(|s| s.width).collect();
+        assert_eq!(widths, [10, 20, 40]);
+        assert_eq!(&srcset[2].url, url);
+        let ContentNode::Link { url: slides, .. } = &children[2] else {
+            panic!("Expected Link");
+        };
+        assert!(
+            slides.starts_with("/sinter_data/assets/slides.") && slides.ends_with(".pdf#page=2")
+        );
+        assert!(matches!(&children[4], ContentNode::Link { url, .. } if url == "other.md"));
+        assert!(matches!(&children[6], ContentNode::Link { url, .. } if url == "../about"));
+        let ContentNode::Image {
+            url,
+            width,
+            height,
+            placeholder,
+            ..
+        } = &children[10]
+        else {
+            panic!("Expected Image");
+        };
+        assert_eq!(url, "/images/a.png");
+        assert_eq!((*width, *height), (Some(30), Some(10)));
+        assert!(placeholder.is_some());
+        assert!(matches!(
+            &children[12],
+            ContentNode::Image {
+                width: Some(7),
+                height: Some(3),
+                ..
+            }
+        ));
+        // Bare domains and pages are links, not files to publish.
+        assert!(matches!(&children[14], ContentNode::Link { url, .. } if url == "rust-lang.org"));
+        assert!(
+            matches!(&children[16], ContentNode::Link { url, .. } if url == "example.com/page.html")
+        );
+
+        let out = tempfile::tempdir().unwrap();
+        let unpublished = dir.path().join("unpublished");
+        assert_eq!(
+            pipeline.write([&post], out.path(), &unpublished).unwrap(),
+            4
+        );
+        let small = srcset[0].url.trim_start_matches("/sinter_data/");
+        assert_eq!(
+            image::image_dimensions(out.path().join(small)).unwrap(),
+            (10, 5)
+        );
+
+        // Variants an earlier build published are copied rather than resized.
+        fs::write(out.path().join(small), b"published").unwrap();
+        let next = tempfile::tempdir().unwrap();
+        assert_eq!(pipeline.write([&post], next.path(), out.path()).unwrap(), 4);
+        assert_eq!(fs::read(next.path().join(small)).unwrap(), b"published");
+
+        // Nothing is written for posts that no longer reference the assets.
+        let out = tempfile::tempdir().unwrap();
+        assert_eq!(pipeline.write([], out.path(), &unpublished).unwrap(), 0);
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sinter_core::Post;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{debug, info, warn};
+
+/// Bump whenever `parse_post` produces a different `Post` for the same input,
+/// so entries written by older compilers are never reused.
+const CACHE_VERSION: u32 = 11;
+
+/// Persistent cache of parsed posts, keyed by the hash of the Markdown source
+/// together with the compiler version and the site configuration. It also
+/// holds the size and placeholder of images, keyed by their content hash.
+///
+/// Each entry is stored as `<key>.json` inside the cache directory. Entries that
+/// are not touched during a build are removed by [`BuildCache::prune`].
+pub struct BuildCache {
+    dir: Option<PathBuf>,
+    salt: String,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    used: Mutex<HashSet<String>>,
+}
+
+impl BuildCache {
+    /// Opens (and creates if needed) the cache at `dir`. `fingerprint` should
+    /// capture every input besides the file itself that affects parsing.
+    pub fn open(dir: &Path, fingerprint: &str) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cache directory {:?}", dir))?;
+
+        let salt = format!(
+            "{}:{}:{}",
+            env!("CARGO_PKG_VERSION"),
+            CACHE_VERSION,
+            blake3::hash(fingerprint.as_bytes()).to_hex()
+        );
+
+        Ok(Self {
+            dir: Some(dir.to_path_buf()),
+            salt,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            used: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// A cache that never stores anything; every lookup is a miss.
+    pub fn disabled() -> Self {
+        Self {
+            dir: None,
+            salt: String::new(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            used: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn key(&self, content: &str) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update(content.as_bytes());
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// Returns the cached post for `content`, or parses it with `parse` and
+    /// stores the result.
+    pub fn get_or_parse<F>(&self, content: &str, parse: F) -> Result<Post>
+    where
+        F: FnOnce(&str) -> Result<Post>,
+    {
+        let Some(dir) = &self.dir else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return parse(content);
+        };
+
+        let key = self.key(content);
+        let entry_path = dir.join(format!("{}.json", key));
+        self.used.lock().unwrap().insert(key);
+
+        if let Some(post) = read_entry(&entry_path) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(post);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let post = parse(content)?;
+        if let Err(e) = write_entry(&entry_path, &post) {
+            warn!("Failed to write cache entry {:?}: {:?}", entry_path, e);
+        }
+        Ok(post)
+    }
+
+    /// Returns the value stored for `content` by [`BuildCache::put`], if any.
+    /// Unlike posts, these lookups are not counted as hits or misses.
+    pub fn get<T: DeserializeOwned>(&self, content: &str) -> Option<T> {
+        let dir = self.dir.as_ref()?;
+        let key = self.key(content);
+        let entry = read_entry(&dir.join(format!("{}.json", key)));
+        self.used.lock().unwrap().insert(key);
+        entry
+    }
+
+    /// Stores `value` under `content`, to be returned by [`BuildCache::get`].
+    pub fn put<T: Serialize>(&self, content: &str, value: &T) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let key = self.key(content);
+        let entry_path = dir.join(format!("{}.json", key));
+        if let Err(e) = write_entry(&entry_path, value) {
+            warn!("Failed to write cache entry {:?}: {:?}", entry_path, e);
+        }
+        self.used.lock().unwrap().insert(key);
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Deletes every entry that was not read or written during this build.
+    pub fn prune(&self) -> Result<usize> {
+        let Some(dir) = &self.dir else {
+            return Ok(0);
+        };
+
+        let used = self.used.lock().unwrap();
+        let mut removed = 0;
+        for entry in fs::read_dir(dir).context("Failed to read cache directory")? {
+            let path = entry?.path();
+            let is_used = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .is_some_and(|stem| used.contains(stem));
+            if !is_used && path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Removes every entry of the cache at `dir`, and the directory itself
+    /// if nothing else is in it. Returns the number of entries removed.
+    pub fn clear(dir: &Path) -> Result<usize> {
+        if !dir.is_dir() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for entry in fs::read_dir(dir).context("Failed to read cache directory")? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        let _ = fs::remove_dir(dir);
+        Ok(removed)
+    }
+
+    pub fn report(&self) {
+        if self.dir.is_none() {
+            info!("Build cache disabled ({} files parsed).", self.misses());
+            return;
+        }
+        info!(
+            "Build cache: {} hit(s), {} miss(es).",
+            self.hits(),
+            self.misses()
+        );
+    }
+}
+
+fn read_entry<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let data = fs::read(path).ok()?;
+    match serde_json::from_slice(&data) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            debug!("Ignoring unreadable cache entry {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+fn write_entry<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let json = serde_json::to_vec(value)?;
+    fs::write(path, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sinter_core::{LiteDate, PostMetadata};
+
+    fn sample_post() -> Post {
+        Post {
+            metadata: PostMetadata {
+                id: "1".to_string(),
+                title: "Cached".to_string(),
+                slug: "cached".to_string(),
+                date: LiteDate {
+                    year: 2024,
+                    month: 1,
+                    day: 1,
+                },
+                tags: vec![],
+                tag_slugs: vec![],
+                draft: false,
+                summary: "s".to_string(),
+                word_count: 0,
+                reading_time: 0,
+            },
+            content_ast: vec![],
+            toc: vec![],
+        }
+    }
+
+    #[test]
+    fn test_cache_hit_and_prune() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let cache = BuildCache::open(dir.path(), "config").unwrap();
+        cache.get_or_parse("a", |_| Ok(sample_post())).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+        let cache = BuildCache::open(dir.path(), "config").unwrap();
+        let post = cache
+            .get_or_parse("a", |_| panic!("should be served from cache"))
+            .unwrap();
+        assert_eq!(post, sample_post());
+        assert_eq!((cache.hits(), cache.misses()), (1, 0));
+
+        // A different fingerprint must not reuse entries, and prunes the old one.
+        let cache = BuildCache::open(dir.path(), "changed").unwrap();
+        cache.get_or_parse("a", |_| Ok(sample_post())).unwrap();
+        assert_eq!(cache.misses(), 1);
+        assert_eq!(cache.prune().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_get_and_put_values() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let cache = BuildCache::open(dir.path(), "config").unwrap();
+        assert_eq!(cache.get::<u32>("image"), None);
+        cache.put("image", &42u32);
+
+        // Values survive into the next build, and count as used there.
+        let cache = BuildCache::open(dir.path(), "config").unwrap();
+        assert_eq!(cache.get::<u32>("image"), Some(42));
+        assert_eq!((cache.hits(), cache.misses()), (0, 0));
+        assert_eq!(cache.prune().unwrap(), 0);
+
+        let disabled = BuildCache::disabled();
+        disabled.put("image", &42u32);
+        assert_eq!(disabled.get::<u32>("image"), None);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Error produced when a post's frontmatter cannot be parsed or deserialized.
+///
+/// `line` and `column` are 1-based positions inside the YAML block, i.e. the
+/// first line after the opening `---`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontmatterError {
+    pub field: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl FrontmatterError {
+    /// Re-parses the raw YAML block as `T` to recover the failing field and its
+    /// position, which the `gray_matter` value tree no longer carries.
+    pub fn explain<T: DeserializeOwned>(yaml: &str, fallback: impl fmt::Display) -> Self {
+        let deserializer = serde_yaml::Deserializer::from_str(yaml);
+        let err = match serde_path_to_error::deserialize::<_, T>(deserializer) {
+            Ok(_) => {
+                return Self {
+                    field: None,
+                    line: None,
+                    column: None,
+                    message: fallback.to_string(),
+                };
+            }
+            Err(err) => err,
+        };
+
+        let path = err.path().to_string();
+        let inner = err.into_inner();
+        let location = inner.location();
+        let mut message = inner.to_string();
+        if let Some(loc) = &location {
+            let suffix = format!(" at line {} column {}", loc.line(), loc.column());
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message = stripped.to_string();
+            }
+        }
+
+        // Missing fields are reported against their parent, so pull the name from the message.
+        let field = if path == "." {
+            message
+                .strip_prefix("missing field `")
+                .and_then(|rest| rest.split('`').next())
+                .map(str::to_string)
+        } else {
+            Some(path)
+        };
+
+        // Errors raised by custom `Deserialize` impls (e.g. `LiteDate`) are not
+        // positioned by serde_yaml, so prefer the line of the offending key.
+        let position = field
+            .as_deref()
+            .and_then(|f| find_key(yaml, f))
+            .or_else(|| location.as_ref().map(|l| (l.line(), l.column())));
+
+        Self {
+            field,
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+            message,
+        }
+    }
+}
+
+/// Finds the 1-based line and value column of a top-level `key:` entry.
+fn find_key(yaml: &str, key: &str) -> Option<(usize, usize)> {
+    yaml.lines().enumerate().find_map(|(i, line)| {
+        let rest = line.strip_prefix(key)?.strip_prefix(':')?;
+        let value_offset = line.len() - rest.trim_start().len();
+        Some((i + 1, value_offset + 1))
+    })
+}
+
+impl fmt::Display for FrontmatterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(field) = &self.field {
+            write!(f, "field `{}`: ", field)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, " (frontmatter line {}, column {})", line, column)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FrontmatterError {}
+
+/// A file that could not be turned into a post.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub error: anyhow::Error,
+}
+
+impl Diagnostic {
+    pub fn frontmatter(&self) -> Option<&FrontmatterError> {
+        self.error.downcast_ref::<FrontmatterError>()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.frontmatter() {
+            Some(fm) => write!(f, "{}: {}", self.file.display(), fm),
+            None => write!(f, "{}: {:#}", self.file.display(), self.error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sinter_core::PostMetadata;
+
+    #[test]
+    fn test_explain_missing_field() {
+        let yaml = "id: \"1\"\ntitle: \"T\"\ndate: \"2024-01-01\"\n";
+        let err = FrontmatterError::explain::<PostMetadata>(yaml, "fallback");
+        assert_eq!(err.field.as_deref(), Some("slug"));
+    }
+
+    #[test]
+    fn test_explain_invalid_value() {
+        let yaml = "id: \"1\"\ntitle: \"T\"\nslug: \"t\"\ndate: \"2024/01/01\"\nsummary: \"s\"\n";
+        let err = FrontmatterError::explain::<PostMetadata>(yaml, "fallback");
+        assert_eq!(err.field.as_deref(), Some("date"));
+        assert_eq!((err.line, err.column), (Some(4), Some(7)));
+    }
+}
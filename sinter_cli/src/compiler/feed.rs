@@ -0,0 +1,258 @@
+use super::html::{escape, render_html};
+use super::{DATA_URL_PATH, FeedSection, SiteSection, post_route};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::json;
+use sinter_core::{LiteDate, Post};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+pub const RSS_FILENAME: &str = "feed.xml";
+pub const ATOM_FILENAME: &str = "atom.xml";
+pub const JSON_FEED_FILENAME: &str = "feed.json";
+
+struct FeedItem {
+    title: String,
+    url: String,
+    date: DateTime<Utc>,
+    tags: Vec<String>,
+    summary: String,
+    /// Rendered HTML body, or `None` when only summaries are published.
+    content_html: Option<String>,
+}
+
+/// Writes the RSS 2.0, Atom and JSON feeds for the newest `feed.limit` posts.
+/// `posts` must already be sorted newest first.
+pub fn write_feeds(
+    posts: &[(Post, String)],
+    site: &SiteSection,
+    feed: &FeedSection,
+    site_url: &str,
+    output_dir: &Path,
+) -> Result<()> {
+    let site_url = site_url.trim_end_matches('/');
+    let items: Vec<FeedItem> = posts
+        .iter()
+        .take(feed.limit)
+        .map(|(post, path)| {
+            let url = format!("{}{}", site_url, post_route(path));
+            FeedItem {
+                title: post.metadata.title.clone(),
+                date: to_datetime(&post.metadata.date),
+                tags: post.metadata.tags.clone(),
+                summary: post.metadata.summary.clone(),
+                content_html: feed
+                    .full_content
+                    .then(|| render_html(&post.content_ast, Some(&url))),
+                url,
+            }
+        })
+        .collect();
+
+    // Derived from the content rather than the clock so unchanged sites produce
+    // byte-identical feeds.
+    let updated = items.first().map(|item| item.date).unwrap_or_default();
+
+    let outputs = [
+        (RSS_FILENAME, rss(&items, site, site_url, updated)),
+        (ATOM_FILENAME, atom(&items, site, site_url, updated)),
+        (JSON_FEED_FILENAME, json_feed(&items, site, site_url)?),
+    ];
+    for (filename, body) in outputs {
+        fs::write(output_dir.join(filename), body)
+            .with_context(|| format!("Failed to write {}", filename))?;
+    }
+
+    info!("Feeds written with {} item(s).", items.len());
+    Ok(())
+}
+
+/// `<link rel="alternate">` tags that let browsers and feed readers discover
+/// the feeds, for the head of the site's pages.
+pub fn alternate_links(site_title: &str) -> String {
+    [
+        (RSS_FILENAME, "application/rss+xml", "RSS"),
+        (ATOM_FILENAME, "application/atom+xml", "Atom"),
+        (JSON_FEED_FILENAME, "application/feed+json", "JSON Feed"),
+    ]
+    .iter()
+    .map(|(filename, mime_type, label)| {
+        format!(
+            "<link rel=\"alternate\" type=\"{}\" title=\"{}\" href=\"{}/{}\">",
+            mime_type,
+            escape(&format!("{} ({})", site_title, label)),
+            DATA_URL_PATH,
+            filename
+        )
+    })
+    .collect()
+}
+
+fn to_datetime(date: &LiteDate) -> DateTime<Utc> {
+    NaiveDate::from_ymd_opt(date.year, date.month.into(), date.day.into())
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+        .unwrap_or_default()
+}
+
+fn feed_url(site_url: &str, filename: &str) -> String {
+    format!("{}{}/{}", site_url, DATA_URL_PATH, filename)
+}
+
+fn rss(items: &[FeedItem], site: &SiteSection, site_url: &str, updated: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+    let _ = writeln!(out, "<title>{}</title>", escape(&site.title));
+    let _ = writeln!(out, "<link>{}/</link>", escape(site_url));
+    let _ = writeln!(
+        out,
+        "<description>{}</description>",
+        escape(&site.description)
+    );
+    let _ = writeln!(
+        out,
+        "<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>",
+        escape(&feed_url(site_url, RSS_FILENAME))
+    );
+    let _ = writeln!(
+        out,
+        "<lastBuildDate>{}</lastBuildDate>",
+        updated.to_rfc2822()
+    );
+
+    for item in items {
+        out.push_str("<item>\n");
+        let _ = writeln!(out, "<title>{}</title>", escape(&item.title));
+        let _ = writeln!(out, "<link>{}</link>", escape(&item.url));
+        let _ = writeln!(
+            out,
+            "<guid isPermaLink=\"true\">{}</guid>",
+            escape(&item.url)
+        );
+        let _ = writeln!(out, "<pubDate>{}</pubDate>", item.date.to_rfc2822());
+        for tag in &item.tags {
+            let _ = writeln!(out, "<category>{}</category>", escape(tag));
+        }
+        let description = item.content_html.as_deref().unwrap_or(&item.summary);
+        let _ = writeln!(out, "<description>{}</description>", escape(description));
+        out.push_str("</item>\n");
+    }
+
+    out.push_str("</channel>\n</rss>\n");
+    out
+}
+
+fn atom(items: &[FeedItem], site: &SiteSection, site_url: &str, updated: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    let _ = writeln!(out, "<title>{}</title>", escape(&site.title));
+    if !site.subtitle.is_empty() {
+        let _ = writeln!(out, "<subtitle>{}</subtitle>", escape(&site.subtitle));
+    }
+    let _ = writeln!(out, "<link href=\"{}/\"/>", escape(site_url));
+    let _ = writeln!(
+        out,
+        "<link rel=\"self\" href=\"{}\"/>",
+        escape(&feed_url(site_url, ATOM_FILENAME))
+    );
+    let _ = writeln!(out, "<id>{}/</id>", escape(site_url));
+    let _ = writeln!(out, "<updated>{}</updated>", updated.to_rfc3339());
+    let _ = writeln!(out, "<author><name>{}</name></author>", escape(&site.title));
+
+    for item in items {
+        out.push_str("<entry>\n");
+        let _ = writeln!(out, "<title>{}</title>", escape(&item.title));
+        let _ = writeln!(out, "<link href=\"{}\"/>", escape(&item.url));
+        let _ = writeln!(out, "<id>{}</id>", escape(&item.url));
+        let _ = writeln!(out, "<published>{}</published>", item.date.to_rfc3339());
+        let _ = writeln!(out, "<updated>{}</updated>", item.date.to_rfc3339());
+        for tag in &item.tags {
+            let _ = writeln!(out, "<category term=\"{}\"/>", escape(tag));
+        }
+        let _ = writeln!(out, "<summary>{}</summary>", escape(&item.summary));
+        if let Some(html) = &item.content_html {
+            let _ = writeln!(out, "<content type=\"html\">{}</content>", escape(html));
+        }
+        out.push_str("</entry>\n");
+    }
+
+    out.push_str("</feed>\n");
+    out
+}
+
+fn json_feed(items: &[FeedItem], site: &SiteSection, site_url: &str) -> Result<String> {
+    let items: Vec<_> = items
+        .iter()
+        .map(|item| {
+            let mut entry = json!({
+                "id": item.url,
+                "url": item.url,
+                "title": item.title,
+                "summary": item.summary,
+                "date_published": item.date.to_rfc3339(),
+                "tags": item.tags,
+            });
+            match &item.content_html {
+                Some(html) => entry["content_html"] = json!(html),
+                None => entry["content_text"] = json!(item.summary),
+            }
+            entry
+        })
+        .collect();
+
+    let feed = json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": site.title,
+        "description": site.description,
+        "home_page_url": format!("{}/", site_url),
+        "feed_url": feed_url(site_url, JSON_FEED_FILENAME),
+        "items": items,
+    });
+    serde_json::to_string(&feed).context("Failed to serialize JSON feed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_post;
+
+    #[test]
+    fn test_write_feeds() {
+        let post = parse_post(
+            "---\nid: \"1\"\ntitle: \"A & B\"\nslug: \"a\"\ndate: \"2024-03-05\"\ntags: [\"rust\"]\nsummary: \"s\"\n---\nHello *world*\n",
+            &Default::default(),
+        )
+        .unwrap();
+        let posts = vec![(post, "posts/a.json".to_string())];
+        let site = SiteSection {
+            title: "Site".to_string(),
+            subtitle: String::new(),
+            description: "d".to_string(),
+        };
+        let feed = FeedSection::default();
+        let dir = tempfile::tempdir().unwrap();
+
+        write_feeds(&posts, &site, &feed, "https://example.com/", dir.path()).unwrap();
+
+        let rss = fs::read_to_string(dir.path().join(RSS_FILENAME)).unwrap();
+        assert!(rss.contains("<title>A &amp; B</title>"));
+        assert!(rss.contains("<link>https://example.com/posts/a</link>"));
+        assert!(rss.contains("<pubDate>Tue, 5 Mar 2024 00:00:00 +0000</pubDate>"));
+        assert!(rss.contains("&lt;em&gt;world&lt;/em&gt;"));
+
+        let atom = fs::read_to_string(dir.path().join(ATOM_FILENAME)).unwrap();
+        assert!(atom.contains("<updated>2024-03-05T00:00:00+00:00</updated>"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join(JSON_FEED_FILENAME)).unwrap())
+                .unwrap();
+        assert_eq!(
+            json["items"][0]["content_html"],
+            "<p>Hello <em>world</em></p>"
+        );
+    }
+}
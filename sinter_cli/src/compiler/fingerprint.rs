@@ -0,0 +1,133 @@
+//! Content-hashed names for the JSON shards, so they can be served with
+//! long-lived immutable cache headers.
+//!
+//! Every shard is renamed to `<stem>.<hash>.json` (or `.msgpack`) and the
+//! renames are recorded in a `DataManifest`, itself written under a hashed
+//! name. `site_data.json` keeps its fixed name and points at the manifest; it
+//! is the only file the client must revalidate.
+
+use anyhow::{Context, Result};
+use sinter_core::DataManifest;
+use sinter_core::constants::{PAGES_DIR, POSTS_DIR, SEARCH_DIR, TAGS_DIR};
+use sinter_core::encoding::DataFormat;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Directories below the data output that hold shards fetched by the client.
+/// Feeds, the sitemap and assets stay out: they have public fixed names or
+/// are hashed already.
+const SHARD_DIRS: [&str; 5] = [POSTS_DIR, "archives", PAGES_DIR, TAGS_DIR, SEARCH_DIR];
+
+/// Hex digits of the content hash kept in file names.
+const HASH_LEN: usize = 10;
+
+/// Renames every shard below `data_dir` to a content-hashed name and writes
+/// the manifest, returning its path relative to `data_dir`.
+pub fn hash_shards(data_dir: &Path) -> Result<String> {
+    // Collected before anything is renamed: renaming inside a directory that
+    // is still being walked can make the new names come up again.
+    let mut shards = Vec::new();
+    for dir in SHARD_DIRS {
+        let dir = data_dir.join(dir);
+        for entry in WalkDir::new(&dir).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let Some(extension) = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .filter(|ext| DataFormat::ALL.iter().any(|f| f.extension() == *ext))
+            else {
+                continue;
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let bytes = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+            let hashed = path.with_extension(format!("{}.{}", hash(&bytes), extension));
+            shards.push((path.to_path_buf(), hashed));
+        }
+    }
+
+    let mut manifest = DataManifest::default();
+    for (path, hashed) in shards {
+        fs::rename(&path, &hashed).with_context(|| format!("Failed to rename {:?}", path))?;
+        manifest
+            .files
+            .insert(rel_path(data_dir, &path), rel_path(data_dir, &hashed));
+    }
+
+    let json = serde_json::to_string(&manifest).context("Failed to serialize data manifest")?;
+    let name = format!("manifest.{}.json", hash(json.as_bytes()));
+    fs::write(data_dir.join(&name), json).context("Failed to write data manifest")?;
+    Ok(name)
+}
+
+fn hash(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex()[..HASH_LEN].to_string()
+}
+
+fn rel_path(base: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(base).unwrap_or(path);
+    rel.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_shards() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("pages")).unwrap();
+        fs::create_dir_all(root.join("tags/rust")).unwrap();
+        fs::write(root.join("pages/page_1.json"), "{}").unwrap();
+        fs::write(root.join("tags/rust/page_1.json"), "[]").unwrap();
+        fs::write(root.join("feed.json"), "{}").unwrap();
+
+        let name = hash_shards(root).unwrap();
+        let manifest: DataManifest =
+            serde_json::from_str(&fs::read_to_string(root.join(&name)).unwrap()).unwrap();
+
+        let page = manifest.resolve("pages/page_1.json");
+        assert!(page.starts_with("pages/page_1.") && page.len() == "pages/page_1..json".len() + 10);
+        assert_eq!(fs::read_to_string(root.join(page)).unwrap(), "{}");
+        assert!(!root.join("pages/page_1.json").exists());
+        assert_ne!(
+            manifest.resolve("tags/rust/page_1.json"),
+            "tags/rust/page_1.json"
+        );
+
+        // Files outside the shard directories keep their public names.
+        assert!(root.join("feed.json").exists());
+        assert_eq!(manifest.resolve("feed.json"), "feed.json");
+        assert_eq!(manifest.files.len(), 2);
+    }
+
+    #[test]
+    fn test_hash_many_shards() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("posts")).unwrap();
+        for i in 0..3000 {
+            fs::write(root.join(format!("posts/post-{}.json", i)), i.to_string()).unwrap();
+        }
+
+        let name = hash_shards(root).unwrap();
+        let manifest: DataManifest =
+            serde_json::from_str(&fs::read_to_string(root.join(&name)).unwrap()).unwrap();
+
+        // Each shard is hashed exactly once and every entry points at a real file.
+        assert_eq!(manifest.files.len(), 3000);
+        for (original, hashed) in &manifest.files {
+            assert!(
+                root.join(hashed).is_file(),
+                "{} -> {} is missing",
+                original,
+                hashed
+            );
+        }
+        assert_eq!(fs::read_dir(root.join("posts")).unwrap().count(), 3000);
+    }
+}
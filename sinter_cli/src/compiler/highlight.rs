@@ -0,0 +1,213 @@
+use sinter_core::{CodeToken, ContentNode, TokenKind};
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// Tokenises fenced code blocks with the grammars bundled into the CLI
+/// (syntect's default syntax set).
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    /// Lowercase names or extensions of the languages to highlight; empty
+    /// means every bundled grammar. The full syntax set is loaded either way.
+    languages: Vec<String>,
+    comment: Scope,
+    string: Scope,
+    /// Scope prefixes checked from the innermost scope outwards, most
+    /// specific first.
+    categories: Vec<(Scope, TokenKind)>,
+}
+
+impl Highlighter {
+    pub fn new(languages: &[String]) -> Self {
+        let scope = |s: &str| Scope::new(s).expect("valid scope selector");
+        Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            languages: languages.iter().map(|l| l.to_lowercase()).collect(),
+            comment: scope("comment"),
+            string: scope("string"),
+            categories: [
+                ("constant.numeric", TokenKind::Number),
+                ("constant", TokenKind::Constant),
+                ("keyword.operator", TokenKind::Operator),
+                ("keyword", TokenKind::Keyword),
+                ("storage", TokenKind::Keyword),
+                ("variable.language", TokenKind::Keyword),
+                ("entity.name.function", TokenKind::Function),
+                ("support.function", TokenKind::Function),
+                ("variable.function", TokenKind::Function),
+                ("support.macro", TokenKind::Function),
+                ("entity.name.tag", TokenKind::Tag),
+                ("entity.other.attribute-name", TokenKind::Attribute),
+                ("entity.name", TokenKind::Type),
+                ("entity.other.inherited-class", TokenKind::Type),
+                ("support.type", TokenKind::Type),
+                ("support.class", TokenKind::Type),
+                ("variable", TokenKind::Variable),
+                ("punctuation", TokenKind::Punctuation),
+            ]
+            .into_iter()
+            .map(|(prefix, kind)| (scope(prefix), kind))
+            .collect(),
+        }
+    }
+
+    /// Fills in `tokens` for every code block in `nodes`.
+    pub fn highlight(&self, nodes: &mut [ContentNode]) {
+        for node in nodes {
+            match node {
+                ContentNode::CodeBlock {
+                    lang: Some(lang),
+                    code_text,
+                    tokens,
+                } => {
+                    if let Some(syntax) = self.syntax_for(lang) {
+                        *tokens = self.tokenize(syntax, code_text).unwrap_or_default();
+                    }
+                }
+                ContentNode::Paragraph { children }
+                | ContentNode::Heading { children, .. }
+                | ContentNode::List { children, .. }
+                | ContentNode::ListItem { children }
+                | ContentNode::BlockQuote { children }
+                | ContentNode::Admonition { children, .. }
+                | ContentNode::FootnoteDefinition { children, .. }
+                | ContentNode::Emphasis { children }
+                | ContentNode::Strong { children }
+                | ContentNode::Strikethrough { children }
+                | ContentNode::Link { children, .. }
+                | ContentNode::Table { children, .. }
+                | ContentNode::TableHead { children }
+                | ContentNode::TableBody { children }
+                | ContentNode::TableRow { children }
+                | ContentNode::TableCell { children, .. } => self.highlight(children),
+                _ => {}
+            }
+        }
+    }
+
+    fn syntax_for(&self, info: &str) -> Option<&SyntaxReference> {
+        // Fence info strings may carry extra attributes: ```rust,ignore
+        let lang = info
+            .split(|c: char| c == ',' || c == '{' || c.is_whitespace())
+            .next()?
+            .to_lowercase();
+        if lang.is_empty() {
+            return None;
+        }
+        let syntax = self.syntaxes.find_syntax_by_token(&lang)?;
+        let allowed = self.languages.is_empty()
+            || self.languages.contains(&lang)
+            || self.languages.contains(&syntax.name.to_lowercase())
+            || syntax
+                .file_extensions
+                .iter()
+                .any(|ext| self.languages.contains(&ext.to_lowercase()));
+        allowed.then_some(syntax)
+    }
+
+    /// Splits `code` into spans of one syntax category each. Returns `None` if
+    /// the grammar fails, and an empty list if nothing would be styled.
+    fn tokenize(&self, syntax: &SyntaxReference, code: &str) -> Option<Vec<CodeToken>> {
+        let mut state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        let mut tokens = Vec::new();
+
+        for line in LinesWithEndings::from(code) {
+            let ops = state.parse_line(line, &self.syntaxes).ok()?;
+            let mut pos = 0;
+            for (index, op) in ops {
+                if index > pos {
+                    push_token(&mut tokens, self.kind_of(&stack), &line[pos..index]);
+                    pos = index;
+                }
+                stack.apply(&op).ok()?;
+            }
+            if pos < line.len() {
+                push_token(&mut tokens, self.kind_of(&stack), &line[pos..]);
+            }
+        }
+
+        if tokens.iter().all(|token| token.kind.is_none()) {
+            tokens.clear();
+        }
+        Some(tokens)
+    }
+
+    fn kind_of(&self, stack: &ScopeStack) -> Option<TokenKind> {
+        let scopes = stack.as_slice();
+        // Comments and strings colour everything inside them, delimiters included.
+        for scope in scopes {
+            if self.comment.is_prefix_of(*scope) {
+                return Some(TokenKind::Comment);
+            }
+            if self.string.is_prefix_of(*scope) {
+                return Some(TokenKind::String);
+            }
+        }
+        scopes.iter().rev().find_map(|scope| {
+            self.categories
+                .iter()
+                .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+                .map(|(_, kind)| *kind)
+        })
+    }
+}
+
+/// Appends `text`, merging it into the previous token when the category
+/// matches. Whitespace is never styled, so it always joins the previous token.
+fn push_token(tokens: &mut Vec<CodeToken>, kind: Option<TokenKind>, text: &str) {
+    match tokens.last_mut() {
+        Some(last) if last.kind == kind || text.trim().is_empty() => last.text.push_str(text),
+        _ => tokens.push(CodeToken {
+            kind,
+            text: text.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_block(lang: &str, code: &str) -> Vec<ContentNode> {
+        vec![ContentNode::CodeBlock {
+            lang: Some(lang.to_string()),
+            code_text: code.to_string(),
+            tokens: Vec::new(),
+        }]
+    }
+
+    fn tokens_of(nodes: &[ContentNode]) -> &[CodeToken] {
+        match &nodes[0] {
+            ContentNode::CodeBlock { tokens, .. } => tokens,
+            other => panic!("unexpected node {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_highlight_code_block() {
+        let code = "fn main() {\n    // hi\n    let s = \"x\";\n}\n";
+        let mut nodes = code_block("rust,ignore", code);
+        Highlighter::new(&[]).highlight(&mut nodes);
+
+        let tokens = tokens_of(&nodes);
+        assert_eq!(
+            tokens.iter().map(|t| t.text.as_str()).collect::<String>(),
+            code
+        );
+        let kind_of = |text: &str| {
+            tokens
+                .iter()
+                .find(|t| t.text.trim() == text)
+                .map(|t| t.kind)
+        };
+        assert_eq!(kind_of("fn"), Some(Some(TokenKind::Keyword)));
+        assert_eq!(kind_of("main"), Some(Some(TokenKind::Function)));
+        assert_eq!(kind_of("// hi"), Some(Some(TokenKind::Comment)));
+        assert_eq!(kind_of("\"x\""), Some(Some(TokenKind::String)));
+
+        // Languages outside the configured list are left plain.
+        let mut nodes = code_block("rust", code);
+        Highlighter::new(&["py".to_string()]).highlight(&mut nodes);
+        assert!(tokens_of(&nodes).is_empty());
+    }
+}
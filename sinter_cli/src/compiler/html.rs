@@ -0,0 +1,364 @@
+use sinter_core::ContentNode;
+use std::fmt::Write;
+
+/// Renders a post body to plain semantic HTML (no theme classes), for
+/// consumers outside the WASM app such as feeds.
+///
+/// When `page_url`, the absolute URL of the post, is set, relative link and
+/// image URLs (`/images/a.png`, `../about`, `#intro`) are resolved against it
+/// so the markup still works when read off-site.
+pub fn render_html(nodes: &[ContentNode], page_url: Option<&str>) -> String {
+    let mut out = String::new();
+    let renderer = HtmlRenderer { page_url };
+    for node in nodes {
+        renderer.node(node, &mut out);
+    }
+    out
+}
+
+/// Escapes text for use in HTML/XML element content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Resolves `url` against the absolute `page_url`, the way a browser would.
+fn resolve_url(page_url: &str, url: &str) -> String {
+    let has_scheme = url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if has_scheme || url.starts_with("//") {
+        return url.to_string();
+    }
+
+    let after_scheme = page_url.find("://").map_or(0, |i| i + 3);
+    let path_start = page_url[after_scheme..]
+        .find(['/', '?', '#'])
+        .map_or(page_url.len(), |i| after_scheme + i);
+    let origin = &page_url[..path_start];
+    let page_path = page_url[path_start..]
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+
+    if url.starts_with('/') {
+        return format!("{}{}", origin, url);
+    }
+    if url.is_empty() || url.starts_with(['?', '#']) {
+        let page_path = if page_path.is_empty() { "/" } else { page_path };
+        return format!("{}{}{}", origin, page_path, url);
+    }
+
+    let (path, suffix) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    let mut segments: Vec<&str> = page_path.split('/').filter(|s| !s.is_empty()).collect();
+    if !page_path.ends_with('/') {
+        segments.pop();
+    }
+    let parts: Vec<&str> = path.split('/').collect();
+    for part in &parts {
+        match *part {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(part),
+        }
+    }
+    let trailing = matches!(parts.last(), Some(&("" | "." | "..")));
+    format!(
+        "{}/{}{}{}",
+        origin,
+        segments.join("/"),
+        if trailing && !segments.is_empty() {
+            "/"
+        } else {
+            ""
+        },
+        suffix
+    )
+}
+
+struct HtmlRenderer<'a> {
+    page_url: Option<&'a str>,
+}
+
+impl HtmlRenderer<'_> {
+    fn url(&self, url: &str) -> String {
+        match self.page_url {
+            Some(page_url) => escape(&resolve_url(page_url, url)),
+            None => escape(url),
+        }
+    }
+
+    fn children(&self, children: &[ContentNode], out: &mut String) {
+        for child in children {
+            self.node(child, out);
+        }
+    }
+
+    fn wrap(&self, tag: &str, children: &[ContentNode], out: &mut String) {
+        let _ = write!(out, "<{}>", tag);
+        self.children(children, out);
+        let _ = write!(out, "</{}>", tag);
+    }
+
+    fn node(&self, node: &ContentNode, out: &mut String) {
+        match node {
+            ContentNode::Paragraph { children } => self.wrap("p", children, out),
+            ContentNode::Heading {
+                level,
+                id,
+                classes,
+                children,
+            } => {
+                let _ = write!(out, "<h{}", level);
+                if let Some(id) = id {
+                    let _ = write!(out, " id=\"{}\"", escape(id));
+                }
+                if !classes.is_empty() {
+                    let _ = write!(out, " class=\"{}\"", escape(&classes.join(" ")));
+                }
+                out.push('>');
+                self.children(children, out);
+                let _ = write!(out, "</h{}>", level);
+            }
+            ContentNode::List { ordered, children } => {
+                self.wrap(if *ordered { "ol" } else { "ul" }, children, out)
+            }
+            ContentNode::ListItem { children } => self.wrap("li", children, out),
+            ContentNode::BlockQuote { children } => self.wrap("blockquote", children, out),
+            ContentNode::Admonition {
+                kind,
+                title,
+                children,
+            } => {
+                let _ = write!(
+                    out,
+                    "<div class=\"admonition admonition-{}\"><p class=\"admonition-title\">{}</p>",
+                    escape(kind),
+                    escape(title)
+                );
+                self.children(children, out);
+                out.push_str("</div>");
+            }
+            ContentNode::FootnoteDefinition {
+                number,
+                id,
+                back_refs,
+                children,
+                ..
+            } => {
+                let _ = write!(
+                    out,
+                    "<div class=\"footnote\" id=\"{}\"><sup>{}</sup>",
+                    escape(id),
+                    number
+                );
+                self.children(children, out);
+                for back_ref in back_refs {
+                    let _ = write!(
+                        out,
+                        "<a class=\"footnote-backref\" href=\"#{}\">\u{21a9}</a>",
+                        escape(back_ref)
+                    );
+                }
+                out.push_str("</div>");
+            }
+            ContentNode::CodeBlock {
+                lang,
+                code_text,
+                tokens,
+            } => {
+                out.push_str("<pre><code");
+                if let Some(lang) = lang.as_deref().filter(|l| !l.is_empty()) {
+                    let _ = write!(out, " class=\"language-{}\"", escape(lang));
+                }
+                out.push('>');
+                if tokens.is_empty() {
+                    out.push_str(&escape(code_text));
+                }
+                for token in tokens {
+                    match token.kind {
+                        Some(kind) => {
+                            let _ = write!(
+                                out,
+                                "<span class=\"{}\">{}</span>",
+                                kind.class(),
+                                escape(&token.text)
+                            );
+                        }
+                        None => out.push_str(&escape(&token.text)),
+                    }
+                }
+                out.push_str("</code></pre>");
+            }
+            ContentNode::Text { value } => out.push_str(&escape(value)),
+            ContentNode::InlineCode { value } => {
+                let _ = write!(out, "<code>{}</code>", escape(value));
+            }
+            ContentNode::Html { value } => out.push_str(value),
+            ContentNode::Math {
+                value,
+                display,
+                mathml,
+            } => {
+                let (tag, class) = if *display {
+                    ("div", "math math-display")
+                } else {
+                    ("span", "math math-inline")
+                };
+                // Without MathML the TeX source is shown as-is.
+                let inner = mathml.clone().unwrap_or_else(|| escape(value));
+                let _ = write!(out, "<{tag} class=\"{class}\">{}</{tag}>", inner);
+            }
+            ContentNode::TaskListMarker { checked } => {
+                out.push_str("<input type=\"checkbox\" disabled");
+                if *checked {
+                    out.push_str(" checked");
+                }
+                out.push_str("> ");
+            }
+            ContentNode::FootnoteReference {
+                number, id, target, ..
+            } => {
+                let _ = write!(
+                    out,
+                    "<sup class=\"footnote-ref\" id=\"{}\"><a href=\"#{}\">{}</a></sup>",
+                    escape(id),
+                    escape(target),
+                    number
+                );
+            }
+            ContentNode::ThematicBreak => out.push_str("<hr>"),
+            ContentNode::Emphasis { children } => self.wrap("em", children, out),
+            ContentNode::Strong { children } => self.wrap("strong", children, out),
+            ContentNode::Strikethrough { children } => self.wrap("del", children, out),
+            ContentNode::Link {
+                url,
+                title,
+                children,
+            } => {
+                let _ = write!(out, "<a href=\"{}\"", self.url(url));
+                if let Some(title) = title {
+                    let _ = write!(out, " title=\"{}\"", escape(title));
+                }
+                out.push('>');
+                self.children(children, out);
+                out.push_str("</a>");
+            }
+            ContentNode::Image {
+                url,
+                title,
+                alt,
+                srcset,
+                width,
+                height,
+                ..
+            } => {
+                let _ = write!(
+                    out,
+                    "<img src=\"{}\" alt=\"{}\"",
+                    self.url(url),
+                    escape(alt)
+                );
+                if let Some(title) = title {
+                    let _ = write!(out, " title=\"{}\"", escape(title));
+                }
+                if !srcset.is_empty() {
+                    let candidates: Vec<_> = srcset
+                        .iter()
+                        .map(|source| format!("{} {}w", self.url(&source.url), source.width))
+                        .collect();
+                    let _ = write!(out, " srcset=\"{}\"", candidates.join(", "));
+                }
+                if let (Some(width), Some(height)) = (width, height) {
+                    let _ = write!(out, " width=\"{}\" height=\"{}\"", width, height);
+                }
+                out.push_str(" loading=\"lazy\">");
+            }
+            ContentNode::Table { children, .. } => self.wrap("table", children, out),
+            ContentNode::TableHead { children } => self.wrap("thead", children, out),
+            ContentNode::TableBody { children } => self.wrap("tbody", children, out),
+            ContentNode::TableRow { children } => self.wrap("tr", children, out),
+            ContentNode::TableCell {
+                header,
+                align,
+                children,
+            } => {
+                let tag = if *header { "th" } else { "td" };
+                let _ = write!(out, "<{}", tag);
+                if *header {
+                    out.push_str(" scope=\"col\"");
+                }
+                if let Some(align) = align.text_align() {
+                    let _ = write!(out, " style=\"text-align: {}\"", align);
+                }
+                out.push('>');
+                self.children(children, out);
+                let _ = write!(out, "</{}>", tag);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_post;
+
+    #[test]
+    fn test_render_html() {
+        let post = parse_post(
+            "---\nid: \"1\"\ntitle: \"T\"\nslug: \"t\"\ndate: \"2024-01-01\"\nsummary: \"s\"\n---\nA *b* & [c](/posts/c \"C\")\n\n![i](/img.png)\n\n| h |\n|---|\n| d |\n",
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            render_html(&post.content_ast, Some("https://example.com/")),
+            "<p>A <em>b</em> &amp; <a href=\"https://example.com/posts/c\" title=\"C\">c</a></p>\
+             <p><img src=\"https://example.com/img.png\" alt=\"i\" loading=\"lazy\"></p>\
+             <table><thead><tr><th scope=\"col\">h</th></tr></thead><tr><td>d</td></tr></table>"
+        );
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let page = "https://example.com/posts/hello";
+        assert_eq!(resolve_url(page, "/img.png"), "https://example.com/img.png");
+        assert_eq!(
+            resolve_url(page, "world"),
+            "https://example.com/posts/world"
+        );
+        assert_eq!(
+            resolve_url(page, "../about#me"),
+            "https://example.com/about#me"
+        );
+        assert_eq!(resolve_url(page, "./"), "https://example.com/posts/");
+        assert_eq!(
+            resolve_url(page, "#intro"),
+            "https://example.com/posts/hello#intro"
+        );
+        assert_eq!(resolve_url(page, "mailto:a@b.c"), "mailto:a@b.c");
+        assert_eq!(
+            resolve_url(page, "//cdn.example.com/x"),
+            "//cdn.example.com/x"
+        );
+        assert_eq!(
+            resolve_url("https://example.com", "a"),
+            "https://example.com/a"
+        );
+    }
+}
@@ -1,15 +1,46 @@
-use pulldown_cmark::{CodeBlockKind, CowStr, Event, HeadingLevel, Tag};
-use sinter_core::ContentNode;
+use pulldown_cmark::{Alignment, CodeBlockKind, CowStr, Event, HeadingLevel, Tag};
+use sinter_core::{ContentNode, TableAlignment};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// GitHub alert kinds and their titles.
+const ALERT_KINDS: [(&str, &str); 5] = [
+    ("note", "Note"),
+    ("tip", "Tip"),
+    ("important", "Important"),
+    ("warning", "Warning"),
+    ("caution", "Caution"),
+];
 
 /// State machine for transforming Markdown events into an AST.
 /// Uses a pushdown automaton (stack-based state machine) to handle nested structures.
-pub struct AstStateMachine {
+pub struct AstStateMachine<'a> {
     stack: Vec<Frame>,
+    /// Custom admonition kinds mapped to their default title.
+    admonitions: &'a BTreeMap<String, String>,
+    /// Stack index of a block quote whose first child has not started yet.
+    pending_quote: Option<usize>,
+    /// First line of a block quote's leading paragraph, scanned for `[!KIND]`.
+    marker_scan: Option<MarkerScan>,
 }
 
 struct Frame {
     tag: Option<FrameType>,
     children: Vec<ContentNode>,
+    /// For block quotes: the text of the first line of the first paragraph.
+    first_line: Option<FirstLine>,
+}
+
+struct MarkerScan {
+    quote: usize,
+    paragraph: usize,
+    text: String,
+    nodes: usize,
+}
+
+struct FirstLine {
+    text: String,
+    /// Leading paragraph nodes making up the line, including its line break.
+    nodes: usize,
 }
 
 enum FrameType {
@@ -19,19 +50,27 @@ enum FrameType {
     Link(String, Option<String>),
     Image(String, Option<String>),
     CodeBlock(Option<String>),
+    FootnoteDefinition(String),
+    BlockQuote,
+    Table(Vec<TableAlignment>),
 }
 
-impl AstStateMachine {
-    pub fn new() -> Self {
+impl<'a> AstStateMachine<'a> {
+    pub fn new(admonitions: &'a BTreeMap<String, String>) -> Self {
         Self {
             stack: vec![Frame {
                 tag: None,
                 children: Vec::new(),
+                first_line: None,
             }],
+            admonitions,
+            pending_quote: None,
+            marker_scan: None,
         }
     }
 
     pub fn consume(&mut self, event: Event) {
+        self.scan_marker(&event);
         match event {
             Event::Start(tag) => self.enter_node(tag),
             Event::End(_) => self.exit_node(),
@@ -46,16 +85,22 @@ impl AstStateMachine {
             Event::InlineMath(text) => self.append_node(ContentNode::Math {
                 value: text.to_string(),
                 display: false,
+                mathml: None,
             }),
             Event::DisplayMath(text) => self.append_node(ContentNode::Math {
                 value: text.to_string(),
                 display: true,
+                mathml: None,
             }),
             Event::TaskListMarker(checked) => {
                 self.append_node(ContentNode::TaskListMarker { checked })
             }
-            // TODO: Add support for Footnotes if needed
-            _ => {}
+            Event::FootnoteReference(label) => self.append_node(ContentNode::FootnoteReference {
+                label: label.to_string(),
+                number: 0,
+                id: String::new(),
+                target: String::new(),
+            }),
         }
     }
 
@@ -64,7 +109,42 @@ impl AstStateMachine {
         while self.stack.len() > 1 {
             self.exit_node();
         }
-        self.stack.pop().unwrap().children
+        let mut nodes = self.stack.pop().unwrap().children;
+        number_footnotes(&mut nodes);
+        nodes
+    }
+
+    /// Tracks the text of the first line of a block quote so `exit_node` can
+    /// tell whether it starts with an admonition marker.
+    fn scan_marker(&mut self, event: &Event) {
+        if let Some(scan) = &mut self.marker_scan {
+            match event {
+                Event::Text(text) if self.stack.len() - 1 == scan.paragraph => {
+                    scan.text.push_str(text);
+                    scan.nodes += 1;
+                    return;
+                }
+                _ => {
+                    let line_break = matches!(event, Event::SoftBreak | Event::HardBreak);
+                    let scan = self.marker_scan.take().unwrap();
+                    self.stack[scan.quote].first_line = Some(FirstLine {
+                        text: scan.text,
+                        nodes: scan.nodes + usize::from(line_break),
+                    });
+                }
+            }
+        }
+        if let Some(quote) = self.pending_quote.take()
+            && matches!(event, Event::Start(Tag::Paragraph))
+            && self.stack.len() - 1 == quote
+        {
+            self.marker_scan = Some(MarkerScan {
+                quote,
+                paragraph: quote + 1,
+                text: String::new(),
+                nodes: 0,
+            });
+        }
     }
 
     fn enter_node(&mut self, tag: Tag) {
@@ -89,9 +169,10 @@ impl AstStateMachine {
                     classes.into_iter().map(|s| s.to_string()).collect(),
                 ))
             }
-            Tag::BlockQuote(_) => Some(FrameType::Container(|c| ContentNode::BlockQuote {
-                children: c,
-            })),
+            Tag::BlockQuote(_) => {
+                self.pending_quote = Some(self.stack.len());
+                Some(FrameType::BlockQuote)
+            }
             Tag::CodeBlock(kind) => {
                 let lang = match kind {
                     CodeBlockKind::Fenced(l) => Some(l.to_string()),
@@ -134,7 +215,17 @@ impl AstStateMachine {
                 };
                 Some(FrameType::Image(dest_url.to_string(), title_opt))
             }
-            Tag::Table(_) => Some(FrameType::Container(|c| ContentNode::Table { children: c })),
+            Tag::Table(alignments) => Some(FrameType::Table(
+                alignments
+                    .into_iter()
+                    .map(|alignment| match alignment {
+                        Alignment::None => TableAlignment::None,
+                        Alignment::Left => TableAlignment::Left,
+                        Alignment::Center => TableAlignment::Center,
+                        Alignment::Right => TableAlignment::Right,
+                    })
+                    .collect(),
+            )),
             Tag::TableHead => Some(FrameType::Container(|c| ContentNode::TableHead {
                 children: c,
             })),
@@ -142,8 +233,13 @@ impl AstStateMachine {
                 children: c,
             })),
             Tag::TableCell => Some(FrameType::Container(|c| ContentNode::TableCell {
+                header: false,
+                align: TableAlignment::None,
                 children: c,
             })),
+            Tag::FootnoteDefinition(label) => {
+                Some(FrameType::FootnoteDefinition(label.to_string()))
+            }
             _ => None,
         };
 
@@ -151,6 +247,7 @@ impl AstStateMachine {
             self.stack.push(Frame {
                 tag: Some(ft),
                 children: Vec::new(),
+                first_line: None,
             });
         }
     }
@@ -182,23 +279,53 @@ impl AstStateMachine {
                         .children
                         .iter()
                         .map(|c| match c {
-                            ContentNode::Text { value } => value.as_str(),
+                            ContentNode::Text { value } | ContentNode::InlineCode { value } => {
+                                value.as_str()
+                            }
                             _ => "",
                         })
                         .collect::<String>();
-                    ContentNode::Image { url, title, alt }
+                    ContentNode::Image {
+                        url,
+                        title,
+                        alt,
+                        srcset: Vec::new(),
+                        width: None,
+                        height: None,
+                        placeholder: None,
+                    }
                 }
                 Some(FrameType::CodeBlock(lang)) => {
                     let code_text = frame
                         .children
                         .iter()
                         .map(|c| match c {
-                            ContentNode::Text { value } => value.as_str(),
+                            ContentNode::Text { value } | ContentNode::InlineCode { value } => {
+                                value.as_str()
+                            }
                             _ => "",
                         })
                         .collect::<String>();
-                    ContentNode::CodeBlock { lang, code_text }
+                    ContentNode::CodeBlock {
+                        lang,
+                        code_text,
+                        tokens: Vec::new(),
+                    }
                 }
+                Some(FrameType::Table(alignments)) => table(alignments, frame.children),
+                Some(FrameType::BlockQuote) => match frame.first_line {
+                    Some(line) => self.admonition(line, frame.children),
+                    None => ContentNode::BlockQuote {
+                        children: frame.children,
+                    },
+                },
+                Some(FrameType::FootnoteDefinition(label)) => ContentNode::FootnoteDefinition {
+                    label,
+                    number: 0,
+                    id: String::new(),
+                    back_refs: Vec::new(),
+                    children: frame.children,
+                },
                 None => unreachable!("Root frame should not be popped via exit_node"),
             };
 
@@ -206,6 +333,48 @@ impl AstStateMachine {
         }
     }
 
+    /// Turns a block quote whose first line is `[!KIND] optional title` into
+    /// an admonition, dropping that line from the body.
+    fn admonition(&self, line: FirstLine, mut children: Vec<ContentNode>) -> ContentNode {
+        let Some((kind, title)) = self.parse_marker(&line.text) else {
+            return ContentNode::BlockQuote { children };
+        };
+        if let Some(ContentNode::Paragraph { children: first }) = children.first_mut() {
+            first.drain(..line.nodes.min(first.len()));
+            if first.is_empty() {
+                children.remove(0);
+            }
+        }
+        ContentNode::Admonition {
+            kind,
+            title,
+            children,
+        }
+    }
+
+    fn parse_marker(&self, line: &str) -> Option<(String, String)> {
+        let rest = line.trim_start().strip_prefix("[!")?;
+        let (kind, title) = rest.split_once(']')?;
+        let kind = kind.to_lowercase();
+        let custom = self
+            .admonitions
+            .iter()
+            .find(|(custom, _)| custom.to_lowercase() == kind);
+        let default_title = match custom {
+            Some((_, title)) if !title.is_empty() => title.clone(),
+            Some(_) => capitalize(&kind),
+            None => ALERT_KINDS
+                .iter()
+                .find(|(k, _)| *k == kind)
+                .map(|(_, title)| title.to_string())?,
+        };
+        let title = match title.trim() {
+            "" => default_title,
+            title => title.to_string(),
+        };
+        Some((kind, title))
+    }
+
     fn append_text(&mut self, text: CowStr) {
         if let Some(top) = self.stack.last_mut() {
             // Optimization: Merge adjacent text nodes?
@@ -217,12 +386,9 @@ impl AstStateMachine {
     }
 
     fn append_inline_code(&mut self, text: CowStr) {
-        if let Some(top) = self.stack.last_mut() {
-            // Currently generic Text, but could be specific InlineCode node in future
-            top.children.push(ContentNode::Text {
-                value: text.to_string(),
-            });
-        }
+        self.append_node(ContentNode::InlineCode {
+            value: text.to_string(),
+        });
     }
 
     fn append_node(&mut self, node: ContentNode) {
@@ -232,9 +398,219 @@ impl AstStateMachine {
     }
 }
 
+/// Numbers footnotes by first reference and fills in their anchor ids, then
+/// moves every definition to the end of the document in number order.
+/// Definitions that are never referenced keep their source order after the
+/// referenced ones; references to undefined labels are left as plain text.
+fn number_footnotes(nodes: &mut Vec<ContentNode>) {
+    let mut definitions = Vec::new();
+    take_definitions(nodes, &mut definitions);
+    if definitions.is_empty() {
+        replace_undefined_references(nodes);
+        return;
+    }
+
+    let mut notes = Footnotes {
+        defined: definitions
+            .iter()
+            .map(|(label, _)| label.to_lowercase())
+            .collect(),
+        ..Footnotes::default()
+    };
+    notes.number_references(nodes);
+    for (_, children) in &mut definitions {
+        notes.number_references(children);
+    }
+
+    let mut numbered = Vec::new();
+    for (label, children) in definitions {
+        let key = label.to_lowercase();
+        // Only the first definition of a label is used, as in CommonMark.
+        if !notes.defined.remove(&key) {
+            continue;
+        }
+        let number = match notes.numbers.get(&key) {
+            Some(&number) => number,
+            None => notes.next_number(key),
+        };
+        numbered.push(ContentNode::FootnoteDefinition {
+            label,
+            number,
+            id: format!("fn-{}", number),
+            back_refs: std::mem::take(&mut notes.back_refs[number - 1]),
+            children,
+        });
+    }
+    numbered.sort_by_key(|node| match node {
+        ContentNode::FootnoteDefinition { number, .. } => *number,
+        _ => 0,
+    });
+    nodes.extend(numbered);
+}
+
+#[derive(Default)]
+struct Footnotes {
+    /// Lowercased labels that have a definition.
+    defined: HashSet<String>,
+    numbers: HashMap<String, usize>,
+    /// Reference ids per footnote, indexed by `number - 1`.
+    back_refs: Vec<Vec<String>>,
+}
+
+impl Footnotes {
+    fn next_number(&mut self, key: String) -> usize {
+        self.back_refs.push(Vec::new());
+        let number = self.back_refs.len();
+        self.numbers.insert(key, number);
+        number
+    }
+
+    fn number_references(&mut self, nodes: &mut [ContentNode]) {
+        for node in nodes {
+            match node {
+                ContentNode::FootnoteReference {
+                    label,
+                    number,
+                    id,
+                    target,
+                } => {
+                    let key = label.to_lowercase();
+                    if !self.defined.contains(&key) {
+                        *node = undefined_reference(label);
+                        continue;
+                    }
+                    *number = match self.numbers.get(&key) {
+                        Some(&number) => number,
+                        None => self.next_number(key),
+                    };
+                    let refs = &mut self.back_refs[*number - 1];
+                    *id = match refs.len() {
+                        0 => format!("fnref-{}", number),
+                        n => format!("fnref-{}-{}", number, n + 1),
+                    };
+                    *target = format!("fn-{}", number);
+                    refs.push(id.clone());
+                }
+                _ => {
+                    if let Some(children) = children_mut(node) {
+                        self.number_references(children);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn take_definitions(nodes: &mut Vec<ContentNode>, out: &mut Vec<(String, Vec<ContentNode>)>) {
+    for mut node in std::mem::take(nodes) {
+        if let ContentNode::FootnoteDefinition {
+            label, children, ..
+        } = node
+        {
+            out.push((label, children));
+            continue;
+        }
+        if let Some(children) = children_mut(&mut node) {
+            take_definitions(children, out);
+        }
+        nodes.push(node);
+    }
+}
+
+fn replace_undefined_references(nodes: &mut [ContentNode]) {
+    for node in nodes {
+        match node {
+            ContentNode::FootnoteReference { label, .. } => *node = undefined_reference(label),
+            _ => {
+                if let Some(children) = children_mut(node) {
+                    replace_undefined_references(children);
+                }
+            }
+        }
+    }
+}
+
+fn undefined_reference(label: &str) -> ContentNode {
+    ContentNode::Text {
+        value: format!("[^{}]", label),
+    }
+}
+
+fn children_mut(node: &mut ContentNode) -> Option<&mut Vec<ContentNode>> {
+    match node {
+        ContentNode::Paragraph { children }
+        | ContentNode::Heading { children, .. }
+        | ContentNode::List { children, .. }
+        | ContentNode::ListItem { children }
+        | ContentNode::BlockQuote { children }
+        | ContentNode::Admonition { children, .. }
+        | ContentNode::FootnoteDefinition { children, .. }
+        | ContentNode::Emphasis { children }
+        | ContentNode::Strong { children }
+        | ContentNode::Strikethrough { children }
+        | ContentNode::Link { children, .. }
+        | ContentNode::Table { children, .. }
+        | ContentNode::TableHead { children }
+        | ContentNode::TableBody { children }
+        | ContentNode::TableRow { children }
+        | ContentNode::TableCell { children, .. } => Some(children),
+        _ => None,
+    }
+}
+
+/// Builds a table node. pulldown-cmark puts the header cells directly under
+/// the head, so they are wrapped in a row here; every cell is then marked with
+/// its column's alignment and whether it is a header cell.
+fn table(alignments: Vec<TableAlignment>, mut children: Vec<ContentNode>) -> ContentNode {
+    for child in &mut children {
+        match child {
+            ContentNode::TableHead { children: head } => {
+                if head
+                    .iter()
+                    .any(|node| matches!(node, ContentNode::TableCell { .. }))
+                {
+                    let cells = std::mem::take(head);
+                    head.push(ContentNode::TableRow { children: cells });
+                }
+                for row in head {
+                    if let ContentNode::TableRow { children: cells } = row {
+                        mark_cells(cells, &alignments, true);
+                    }
+                }
+            }
+            ContentNode::TableRow { children: cells } => mark_cells(cells, &alignments, false),
+            _ => {}
+        }
+    }
+    ContentNode::Table {
+        alignments,
+        children,
+    }
+}
+
+fn mark_cells(cells: &mut [ContentNode], alignments: &[TableAlignment], is_header: bool) {
+    for (column, cell) in cells.iter_mut().enumerate() {
+        if let ContentNode::TableCell { header, align, .. } = cell {
+            *header = is_header;
+            *align = alignments.get(column).copied().unwrap_or_default();
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 /// Convenience function to parse
-pub fn parse(parser: pulldown_cmark::Parser) -> Vec<ContentNode> {
-    let mut machine = AstStateMachine::new();
+pub fn parse(
+    parser: pulldown_cmark::Parser,
+    admonitions: &BTreeMap<String, String>,
+) -> Vec<ContentNode> {
+    let mut machine = AstStateMachine::new(admonitions);
     for event in parser {
         machine.consume(event);
     }
@@ -252,8 +628,13 @@ mod tests {
         options.insert(Options::ENABLE_TABLES);
         options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
         options.insert(Options::ENABLE_MATH);
+        options.insert(Options::ENABLE_FOOTNOTES);
         let parser = Parser::new_ext(md, options);
-        parse(parser)
+        let custom = BTreeMap::from([
+            ("info".to_string(), String::new()),
+            ("danger".to_string(), "Danger zone".to_string()),
+        ]);
+        parse(parser, &custom)
     }
 
     #[test]
@@ -297,7 +678,7 @@ mod tests {
         let ast = parse_md(md);
         match &ast[0] {
             ContentNode::Paragraph { children } => match &children[0] {
-                ContentNode::Math { value, display } => {
+                ContentNode::Math { value, display, .. } => {
                     assert_eq!(value, "E=mc^2");
                     assert!(!display);
                 }
@@ -306,4 +687,159 @@ mod tests {
             _ => panic!("Expected Paragraph enum {:#?}", ast[0]),
         }
     }
+
+    #[test]
+    fn test_admonitions() {
+        let md = "> [!WARNING]\n> Mind the *gap*.\n\n> [!info] Heads up\n\n> [!danger]\n> x\n\n> [!unknown]\n> y\n";
+        let ast = parse_md(md);
+
+        let heads: Vec<_> = ast
+            .iter()
+            .map(|node| match node {
+                ContentNode::Admonition {
+                    kind,
+                    title,
+                    children,
+                } => (kind.as_str(), title.as_str(), children.len()),
+                ContentNode::BlockQuote { .. } => ("blockquote", "", 0),
+                other => panic!("Unexpected node {:#?}", other),
+            })
+            .collect();
+        assert_eq!(
+            heads,
+            [
+                ("warning", "Warning", 1),
+                ("info", "Heads up", 0),
+                ("danger", "Danger zone", 1),
+                ("blockquote", "", 0)
+            ]
+        );
+
+        // The marker line is dropped; the rest of the paragraph is kept.
+        let ContentNode::Admonition { children, .. } = &ast[0] else {
+            unreachable!()
+        };
+        let ContentNode::Paragraph { children } = &children[0] else {
+            panic!("Expected Paragraph {:#?}", children[0]);
+        };
+        assert_eq!(
+            children[0],
+            ContentNode::Text {
+                value: "Mind the ".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_inline_code() {
+        let ast = parse_md("Call `foo()` now.");
+        match &ast[0] {
+            ContentNode::Paragraph { children } => {
+                assert_eq!(
+                    children[1],
+                    ContentNode::InlineCode {
+                        value: "foo()".to_string()
+                    }
+                );
+            }
+            _ => panic!("Expected Paragraph"),
+        }
+
+        // JSON written before the variant existed still loads.
+        let old: Vec<ContentNode> = serde_json::from_str(
+            r#"[{"type":"paragraph","children":[{"type":"text","value":"foo()"}]}]"#,
+        )
+        .unwrap();
+        assert_eq!(old.len(), 1);
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let md = "B[^b] then A[^a], B again[^B] and [^missing].\n\n[^a]: Alpha.\n[^unused]: Never.\n[^b]: Beta.\n";
+        let ast = parse_md(md);
+
+        let ContentNode::Paragraph { children } = &ast[0] else {
+            panic!("Expected Paragraph {:#?}", ast[0]);
+        };
+        let refs: Vec<_> = children
+            .iter()
+            .filter_map(|c| match c {
+                ContentNode::FootnoteReference {
+                    number, id, target, ..
+                } => Some((*number, id.as_str(), target.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            refs,
+            [
+                (1, "fnref-1", "fn-1"),
+                (2, "fnref-2", "fn-2"),
+                (1, "fnref-1-2", "fn-1")
+            ]
+        );
+        let text: String = children
+            .iter()
+            .filter_map(|c| match c {
+                ContentNode::Text { value } => Some(value.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(text.contains("[^missing]"));
+
+        let defs: Vec<_> = ast[1..]
+            .iter()
+            .map(|c| match c {
+                ContentNode::FootnoteDefinition {
+                    label,
+                    number,
+                    back_refs,
+                    ..
+                } => (label.as_str(), *number, back_refs.len()),
+                other => panic!("Expected FootnoteDefinition {:#?}", other),
+            })
+            .collect();
+        assert_eq!(defs, [("b", 1, 2), ("a", 2, 1), ("unused", 3, 0)]);
+    }
+
+    #[test]
+    fn test_table_alignment() {
+        let ast = parse_md("| l | c | r | n |\n|:--|:-:|--:|---|\n| 1 | 2 | 3 | 4 |\n");
+
+        let ContentNode::Table {
+            alignments,
+            children,
+        } = &ast[0]
+        else {
+            panic!("Expected Table {:#?}", ast[0]);
+        };
+        let columns = [
+            TableAlignment::Left,
+            TableAlignment::Center,
+            TableAlignment::Right,
+            TableAlignment::None,
+        ];
+        assert_eq!(alignments, &columns);
+
+        let cells = |row: &ContentNode| -> Vec<(bool, TableAlignment)> {
+            let ContentNode::TableRow { children } = row else {
+                panic!("Expected TableRow {:#?}", row);
+            };
+            children
+                .iter()
+                .map(|cell| match cell {
+                    ContentNode::TableCell { header, align, .. } => (*header, *align),
+                    other => panic!("Expected TableCell {:#?}", other),
+                })
+                .collect()
+        };
+
+        let ContentNode::TableHead { children: head } = &children[0] else {
+            panic!("Expected TableHead {:#?}", children[0]);
+        };
+        assert_eq!(head.len(), 1, "header cells are wrapped in one row");
+        assert_eq!(cells(&head[0]), columns.map(|align| (true, align)));
+
+        assert_eq!(cells(&children[1]), columns.map(|align| (false, align)));
+    }
 }
@@ -0,0 +1,1203 @@
+//! Build-time TeX to MathML conversion for `ContentNode::Math`.
+//!
+//! Covers the subset of LaTeX math that shows up in posts: scripts, fractions,
+//! roots, big operators, `\left`/`\right`, matrix-like environments, font
+//! variants, accents and spacing. Anything else is reported as an error so the
+//! build can point at it instead of shipping broken markup.
+
+use super::html::escape;
+use sinter_core::ContentNode;
+use std::fmt;
+
+/// Deepest nesting of groups, arguments and environments accepted. The parser
+/// is recursive, so this keeps hostile input from overflowing the stack.
+const MAX_NESTING: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MathError {
+    UnsupportedMacro(String),
+    UnsupportedEnvironment(String),
+    Syntax(&'static str),
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathError::UnsupportedMacro(name) => write!(f, "unsupported macro `\\{}`", name),
+            MathError::UnsupportedEnvironment(name) => {
+                write!(f, "unsupported environment `{}`", name)
+            }
+            MathError::Syntax(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+/// Fills in `mathml` for every math node in `nodes`. Returns the expressions
+/// that could not be converted; those keep only their TeX source.
+pub fn render_math(nodes: &mut [ContentNode]) -> Vec<(String, MathError)> {
+    let mut failures = Vec::new();
+    render_nodes(nodes, &mut failures);
+    failures
+}
+
+fn render_nodes(nodes: &mut [ContentNode], failures: &mut Vec<(String, MathError)>) {
+    for node in nodes {
+        match node {
+            ContentNode::Math {
+                value,
+                display,
+                mathml,
+            } => match to_mathml(value, *display) {
+                Ok(markup) => *mathml = Some(markup),
+                Err(error) => failures.push((value.clone(), error)),
+            },
+            ContentNode::Paragraph { children }
+            | ContentNode::Heading { children, .. }
+            | ContentNode::List { children, .. }
+            | ContentNode::ListItem { children }
+            | ContentNode::BlockQuote { children }
+            | ContentNode::Admonition { children, .. }
+            | ContentNode::FootnoteDefinition { children, .. }
+            | ContentNode::Emphasis { children }
+            | ContentNode::Strong { children }
+            | ContentNode::Strikethrough { children }
+            | ContentNode::Link { children, .. }
+            | ContentNode::Table { children, .. }
+            | ContentNode::TableHead { children }
+            | ContentNode::TableBody { children }
+            | ContentNode::TableRow { children }
+            | ContentNode::TableCell { children, .. } => render_nodes(children, failures),
+            _ => {}
+        }
+    }
+}
+
+/// Converts a TeX expression (without `$` delimiters) to a `<math>` element.
+pub fn to_mathml(tex: &str, display: bool) -> Result<String, MathError> {
+    let mut parser = Parser::new(tex, display);
+    let rows = parser.parse_table()?;
+    match parser.next() {
+        Token::Eof => {}
+        Token::Close => return Err(MathError::Syntax("unbalanced `}`")),
+        _ => return Err(MathError::Syntax("unmatched `\\right` or `\\end`")),
+    }
+
+    let body = if rows.len() == 1 && rows[0].len() == 1 {
+        mrow(rows.into_iter().flatten().flatten().collect())
+    } else {
+        // Bare `\\` and `&` at the top level behave like an `aligned` block.
+        table(rows, Some("right left"))
+    };
+    let mode = if display { "block" } else { "inline" };
+    Ok(format!("<math display=\"{}\">{}</math>", mode, body))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Command(String),
+    Letter(char),
+    Digit(char),
+    Symbol(char),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Amp,
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    Normal,
+    Bold,
+    Italic,
+    BoldItalic,
+    Script,
+    Fraktur,
+    DoubleStruck,
+    SansSerif,
+    Monospace,
+}
+
+/// A parsed item that scripts can attach to. `limits` places scripts above and
+/// below instead of to the side (`\sum` and `\lim` in display mode).
+struct Atom {
+    xml: String,
+    limits: bool,
+}
+
+impl Atom {
+    fn new(xml: String) -> Self {
+        Self { xml, limits: false }
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    display: bool,
+    variant: Option<Variant>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str, display: bool) -> Self {
+        Self {
+            src,
+            pos: 0,
+            display,
+            variant: None,
+            depth: 0,
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek_char() {
+            if c.is_whitespace() {
+                self.pos += c.len_utf8();
+            } else if c == '%' {
+                self.pos = self.src[self.pos..]
+                    .find('\n')
+                    .map_or(self.src.len(), |end| self.pos + end);
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next(&mut self) -> Token {
+        self.skip_whitespace();
+        let Some(c) = self.peek_char() else {
+            return Token::Eof;
+        };
+        self.pos += c.len_utf8();
+        match c {
+            '\\' => {
+                let name: String = self.src[self.pos..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .collect();
+                if !name.is_empty() {
+                    self.pos += name.len();
+                    return Token::Command(name);
+                }
+                match self.peek_char() {
+                    Some(c) => {
+                        self.pos += c.len_utf8();
+                        Token::Command(c.to_string())
+                    }
+                    None => Token::Symbol('\\'),
+                }
+            }
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '^' => Token::Sup,
+            '_' => Token::Sub,
+            '&' => Token::Amp,
+            c if c.is_ascii_digit() => Token::Digit(c),
+            c if c.is_alphabetic() => Token::Letter(c),
+            c => Token::Symbol(c),
+        }
+    }
+
+    fn peek(&mut self) -> Token {
+        let pos = self.pos;
+        let token = self.next();
+        self.pos = pos;
+        token
+    }
+
+    fn expect_close(&mut self) -> Result<(), MathError> {
+        match self.next() {
+            Token::Close => Ok(()),
+            _ => Err(MathError::Syntax("missing `}`")),
+        }
+    }
+
+    /// Reads a `{...}` group verbatim, for `\text` and environment names.
+    fn raw_group(&mut self) -> Result<String, MathError> {
+        self.skip_whitespace();
+        if self.peek_char() != Some('{') {
+            return Err(MathError::Syntax("expected `{`"));
+        }
+        self.pos += 1;
+        let mut depth = 0;
+        let mut out = String::new();
+        let mut chars = self.src[self.pos..].chars();
+        while let Some(c) = chars.next() {
+            self.pos += c.len_utf8();
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        self.pos += escaped.len_utf8();
+                        out.push(escaped);
+                    }
+                }
+                '{' => {
+                    depth += 1;
+                    out.push(c);
+                }
+                '}' if depth == 0 => return Ok(out),
+                '}' => {
+                    depth -= 1;
+                    out.push(c);
+                }
+                _ => out.push(c),
+            }
+        }
+        Err(MathError::Syntax("missing `}`"))
+    }
+
+    /// Reads an optional `[...]` argument verbatim.
+    fn optional_arg(&mut self) -> Result<Option<String>, MathError> {
+        self.skip_whitespace();
+        if self.peek_char() != Some('[') {
+            return Ok(None);
+        }
+        let end = self.src[self.pos..]
+            .find(']')
+            .ok_or(MathError::Syntax("missing `]`"))?;
+        let arg = self.src[self.pos + 1..self.pos + end].to_string();
+        self.pos += end + 1;
+        Ok(Some(arg))
+    }
+
+    /// Cells of a `&`/`\\` separated block, as rows of cells of items.
+    fn parse_table(&mut self) -> Result<Vec<Vec<Vec<String>>>, MathError> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        loop {
+            row.push(self.parse_row()?);
+            match self.peek() {
+                Token::Amp => {
+                    self.next();
+                }
+                Token::Command(name) if name == "\\" => {
+                    self.next();
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => {
+                    rows.push(row);
+                    break;
+                }
+            }
+        }
+        // A trailing `\\` does not start a new row.
+        if rows.len() > 1 && rows.last().is_some_and(|r| r.len() == 1 && r[0].is_empty()) {
+            rows.pop();
+        }
+        Ok(rows)
+    }
+
+    /// Parses items until the end of the current group, cell or fence.
+    fn parse_row(&mut self) -> Result<Vec<String>, MathError> {
+        let mut items = Vec::new();
+        loop {
+            match self.peek() {
+                Token::Eof | Token::Close | Token::Amp => break,
+                Token::Command(name)
+                    if matches!(name.as_str(), "\\" | "right" | "middle" | "end") =>
+                {
+                    break;
+                }
+                _ => {}
+            }
+            if let Some(item) = self.parse_scripted()? {
+                items.push(item);
+            }
+        }
+        Ok(items)
+    }
+
+    fn parse_scripted(&mut self) -> Result<Option<String>, MathError> {
+        let base = match self.peek() {
+            Token::Sup | Token::Sub => Atom::new("<mrow></mrow>".to_string()),
+            _ => self.parse_atom()?,
+        };
+        let (mut sub, mut sup) = (None, None);
+        loop {
+            match self.peek() {
+                Token::Sub if sub.is_none() => {
+                    self.next();
+                    sub = Some(self.parse_arg()?);
+                }
+                Token::Sup if sup.is_none() => {
+                    self.next();
+                    sup = Some(self.parse_arg()?);
+                }
+                Token::Sub | Token::Sup => {
+                    return Err(MathError::Syntax("double subscript or superscript"));
+                }
+                _ => break,
+            }
+        }
+
+        let xml = match (sub, sup) {
+            (None, None) if base.xml.is_empty() => return Ok(None),
+            (None, None) => base.xml,
+            (Some(sub), None) => {
+                let tag = if base.limits { "munder" } else { "msub" };
+                format!("<{tag}>{}{}</{tag}>", base.xml, sub)
+            }
+            (None, Some(sup)) => {
+                let tag = if base.limits { "mover" } else { "msup" };
+                format!("<{tag}>{}{}</{tag}>", base.xml, sup)
+            }
+            (Some(sub), Some(sup)) => {
+                let tag = if base.limits { "munderover" } else { "msubsup" };
+                format!("<{tag}>{}{}{}</{tag}>", base.xml, sub, sup)
+            }
+        };
+        Ok(Some(xml))
+    }
+
+    /// A command or script argument: a group, a command or a single character.
+    fn parse_arg(&mut self) -> Result<String, MathError> {
+        match self.peek() {
+            Token::Digit(digit) => {
+                self.next();
+                Ok(self.mn(&digit.to_string()))
+            }
+            Token::Eof | Token::Close | Token::Amp | Token::Sup | Token::Sub => {
+                Err(MathError::Syntax("missing argument"))
+            }
+            _ => {
+                let atom = self.parse_atom()?;
+                Ok(if atom.xml.is_empty() {
+                    "<mrow></mrow>".to_string()
+                } else {
+                    atom.xml
+                })
+            }
+        }
+    }
+
+    /// Every nested construct is parsed through here, so this is where the
+    /// nesting depth is tracked.
+    fn parse_atom(&mut self) -> Result<Atom, MathError> {
+        if self.depth >= MAX_NESTING {
+            return Err(MathError::Syntax("expression nested too deeply"));
+        }
+        self.depth += 1;
+        let atom = self.parse_single_atom();
+        self.depth -= 1;
+        atom
+    }
+
+    fn parse_single_atom(&mut self) -> Result<Atom, MathError> {
+        match self.next() {
+            Token::Open => {
+                let items = self.parse_row()?;
+                self.expect_close()?;
+                Ok(Atom::new(mrow(items)))
+            }
+            Token::Digit(digit) => {
+                let mut number = digit.to_string();
+                let mut rest = self.src[self.pos..].chars().peekable();
+                while let Some(c) = rest.next() {
+                    let decimal = c == '.' && rest.peek().is_some_and(|n| n.is_ascii_digit());
+                    if !c.is_ascii_digit() && !decimal {
+                        break;
+                    }
+                    number.push(c);
+                }
+                self.pos += number.len() - 1;
+                Ok(Atom::new(self.mn(&number)))
+            }
+            Token::Letter(c) => Ok(Atom::new(self.mi(c))),
+            Token::Symbol(c) => Ok(Atom::new(mo_symbol(c))),
+            Token::Command(name) => self.parse_command(&name),
+            Token::Close => Err(MathError::Syntax("unbalanced `}`")),
+            Token::Amp => Err(MathError::Syntax("`&` outside of an environment")),
+            Token::Sup | Token::Sub => Err(MathError::Syntax("missing script base")),
+            Token::Eof => Err(MathError::Syntax("unexpected end of expression")),
+        }
+    }
+
+    fn mi(&self, c: char) -> String {
+        match self.variant {
+            None => format!("<mi>{}</mi>", escape(&c.to_string())),
+            Some(Variant::Normal) => {
+                format!("<mi mathvariant=\"normal\">{}</mi>", escape(&c.to_string()))
+            }
+            Some(variant) => format!("<mi>{}</mi>", styled(variant, c)),
+        }
+    }
+
+    fn mn(&self, number: &str) -> String {
+        let number: String = match self.variant {
+            Some(variant) => number.chars().map(|c| styled(variant, c)).collect(),
+            None => number.to_string(),
+        };
+        format!("<mn>{}</mn>", number)
+    }
+
+    fn with_variant(&mut self, variant: Variant) -> Result<String, MathError> {
+        let outer = self.variant.replace(variant);
+        let arg = self.parse_arg();
+        self.variant = outer;
+        arg
+    }
+
+    fn parse_delimiter(&mut self) -> Result<Option<String>, MathError> {
+        let delimiter = match self.next() {
+            Token::Symbol('.') => return Ok(None),
+            Token::Symbol(c) => c.to_string(),
+            Token::Command(name) => match name.as_str() {
+                "{" | "lbrace" => "{".to_string(),
+                "}" | "rbrace" => "}".to_string(),
+                "|" | "Vert" | "lVert" | "rVert" => "\u{2016}".to_string(),
+                "vert" | "lvert" | "rvert" => "|".to_string(),
+                "langle" => "\u{27E8}".to_string(),
+                "rangle" => "\u{27E9}".to_string(),
+                "lfloor" => "\u{230A}".to_string(),
+                "rfloor" => "\u{230B}".to_string(),
+                "lceil" => "\u{2308}".to_string(),
+                "rceil" => "\u{2309}".to_string(),
+                "backslash" => "\\".to_string(),
+                _ => return Err(MathError::UnsupportedMacro(name)),
+            },
+            _ => return Err(MathError::Syntax("expected a delimiter")),
+        };
+        Ok(Some(delimiter))
+    }
+
+    fn parse_fenced(&mut self) -> Result<Atom, MathError> {
+        let fence = |delimiter: Option<String>| match delimiter {
+            Some(d) => format!("<mo fence=\"true\" stretchy=\"true\">{}</mo>", escape(&d)),
+            None => String::new(),
+        };
+        let mut items = vec![fence(self.parse_delimiter()?)];
+        loop {
+            items.extend(self.parse_row()?);
+            match self.next() {
+                Token::Command(name) if name == "middle" => {
+                    items.push(fence(self.parse_delimiter()?));
+                }
+                Token::Command(name) if name == "right" => {
+                    items.push(fence(self.parse_delimiter()?));
+                    break;
+                }
+                _ => return Err(MathError::Syntax("`\\left` without matching `\\right`")),
+            }
+        }
+        Ok(Atom::new(format!("<mrow>{}</mrow>", items.concat())))
+    }
+
+    fn parse_environment(&mut self) -> Result<Atom, MathError> {
+        let name = self.raw_group()?;
+        let (open, close, align) = match name.as_str() {
+            "matrix" | "smallmatrix" => (None, None, None),
+            "pmatrix" => (Some("("), Some(")"), None),
+            "bmatrix" => (Some("["), Some("]"), None),
+            "Bmatrix" => (Some("{"), Some("}"), None),
+            "vmatrix" => (Some("|"), Some("|"), None),
+            "Vmatrix" => (Some("\u{2016}"), Some("\u{2016}"), None),
+            "cases" => (Some("{"), None, Some("left left")),
+            "aligned" | "align" | "align*" | "split" => (None, None, Some("right left")),
+            "gathered" | "gather" | "gather*" => (None, None, None),
+            "array" => {
+                // The column spec only affects alignment; cells are centred.
+                self.raw_group()?;
+                (None, None, None)
+            }
+            _ => return Err(MathError::UnsupportedEnvironment(name)),
+        };
+
+        let rows = self.parse_table()?;
+        match self.next() {
+            Token::Command(end) if end == "end" => {
+                if self.raw_group()? != name {
+                    return Err(MathError::Syntax("mismatched `\\end`"));
+                }
+            }
+            _ => return Err(MathError::Syntax("missing `\\end`")),
+        }
+
+        let fence = |d: Option<&str>| {
+            d.map(|d| format!("<mo fence=\"true\" stretchy=\"true\">{}</mo>", escape(d)))
+                .unwrap_or_default()
+        };
+        Ok(Atom::new(format!(
+            "<mrow>{}{}{}</mrow>",
+            fence(open),
+            table(rows, align),
+            fence(close)
+        )))
+    }
+
+    fn parse_command(&mut self, name: &str) -> Result<Atom, MathError> {
+        if let Some(letter) = greek(name) {
+            // Capital Greek letters are upright in TeX.
+            let xml = if letter.is_uppercase() {
+                format!("<mi mathvariant=\"normal\">{}</mi>", letter)
+            } else {
+                format!("<mi>{}</mi>", letter)
+            };
+            return Ok(Atom::new(xml));
+        }
+        if let Some(symbol) = identifier(name) {
+            return Ok(Atom::new(format!("<mi>{}</mi>", symbol)));
+        }
+        if let Some(symbol) = operator(name) {
+            return Ok(Atom::new(format!("<mo>{}</mo>", escape(symbol))));
+        }
+        if let Some((symbol, limits)) = large_operator(name) {
+            return Ok(Atom {
+                xml: format!("<mo>{}</mo>", symbol),
+                limits: limits && self.display,
+            });
+        }
+        if let Some((text, limits)) = function(name) {
+            return Ok(Atom {
+                xml: format!("<mi>{}</mi>", text),
+                limits: limits && self.display,
+            });
+        }
+        if let Some(variant) = font_variant(name) {
+            return Ok(Atom::new(self.with_variant(variant)?));
+        }
+        if let Some((accent, stretchy)) = accent(name) {
+            let base = self.parse_arg()?;
+            let stretchy = if stretchy { " stretchy=\"true\"" } else { "" };
+            return Ok(Atom::new(format!(
+                "<mover accent=\"true\">{}<mo{}>{}</mo></mover>",
+                base, stretchy, accent
+            )));
+        }
+        if let Some(width) = space(name) {
+            return Ok(Atom::new(format!("<mspace width=\"{}\"/>", width)));
+        }
+        if let Some(size) = big_size(name) {
+            let delimiter = self.parse_delimiter()?.unwrap_or_default();
+            return Ok(Atom::new(format!(
+                "<mo minsize=\"{0}\" maxsize=\"{0}\">{1}</mo>",
+                size,
+                escape(&delimiter)
+            )));
+        }
+
+        let xml = match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.parse_arg()?;
+                let denominator = self.parse_arg()?;
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let top = self.parse_arg()?;
+                let bottom = self.parse_arg()?;
+                format!(
+                    "<mrow><mo>(</mo><mfrac linethickness=\"0\">{}{}</mfrac><mo>)</mo></mrow>",
+                    top, bottom
+                )
+            }
+            "sqrt" => match self.optional_arg()? {
+                Some(index) => {
+                    let index = Parser::new(&index, false).parse_row().map(mrow)?;
+                    let radicand = self.parse_arg()?;
+                    format!("<mroot>{}{}</mroot>", radicand, index)
+                }
+                None => format!("<msqrt>{}</msqrt>", self.parse_arg()?),
+            },
+            "text" | "textrm" | "textnormal" | "textit" | "textbf" | "mbox" => {
+                format!("<mtext>{}</mtext>", escape(&self.raw_group()?))
+            }
+            "operatorname" => {
+                format!("<mi>{}</mi>", escape(self.raw_group()?.trim()))
+            }
+            "overline" => format!(
+                "<mover accent=\"true\">{}<mo stretchy=\"true\">\u{203E}</mo></mover>",
+                self.parse_arg()?
+            ),
+            "underline" => format!(
+                "<munder accentunder=\"true\">{}<mo stretchy=\"true\">_</mo></munder>",
+                self.parse_arg()?
+            ),
+            "overbrace" | "underbrace" => {
+                let base = self.parse_arg()?;
+                let (tag, brace) = if name == "overbrace" {
+                    ("mover", '\u{23DE}')
+                } else {
+                    ("munder", '\u{23DF}')
+                };
+                return Ok(Atom {
+                    xml: format!(
+                        "<{tag}>{}<mo stretchy=\"true\">{}</mo></{tag}>",
+                        base, brace
+                    ),
+                    limits: true,
+                });
+            }
+            "overset" | "stackrel" => {
+                let over = self.parse_arg()?;
+                let base = self.parse_arg()?;
+                format!("<mover>{}{}</mover>", base, over)
+            }
+            "underset" => {
+                let under = self.parse_arg()?;
+                let base = self.parse_arg()?;
+                format!("<munder>{}{}</munder>", base, under)
+            }
+            "not" => {
+                let negated = self.parse_atom()?.xml;
+                match negated.strip_suffix("</mo>") {
+                    Some(symbol) => format!("{}\u{338}</mo>", symbol),
+                    None => return Err(MathError::Syntax("`\\not` must precede a relation")),
+                }
+            }
+            "bmod" => "<mo>mod</mo>".to_string(),
+            "pmod" => format!(
+                "<mrow><mo>(</mo><mo>mod</mo>{}<mo>)</mo></mrow>",
+                self.parse_arg()?
+            ),
+            "left" => return self.parse_fenced(),
+            "begin" => return self.parse_environment(),
+            "right" | "middle" => {
+                return Err(MathError::Syntax("`\\right` without matching `\\left`"));
+            }
+            "end" => return Err(MathError::Syntax("`\\end` without matching `\\begin`")),
+            // Style switches have no MathML Core equivalent worth emitting.
+            "displaystyle" | "textstyle" | "scriptstyle" | "limits" | "nolimits" => String::new(),
+            "{" | "}" | "|" => {
+                let delimiter = if name == "|" { "\u{2016}" } else { name };
+                format!("<mo stretchy=\"false\">{}</mo>", delimiter)
+            }
+            "#" | "$" | "%" | "&" | "_" => format!("<mo>{}</mo>", escape(name)),
+            _ => return Err(MathError::UnsupportedMacro(name.to_string())),
+        };
+        Ok(Atom::new(xml))
+    }
+}
+
+fn mrow(mut items: Vec<String>) -> String {
+    if items.len() == 1 {
+        items.remove(0)
+    } else {
+        format!("<mrow>{}</mrow>", items.concat())
+    }
+}
+
+fn table(rows: Vec<Vec<Vec<String>>>, align: Option<&str>) -> String {
+    let mut out = match align {
+        Some(align) => format!("<mtable columnalign=\"{}\">", align),
+        None => "<mtable>".to_string(),
+    };
+    for row in rows {
+        out.push_str("<mtr>");
+        for cell in row {
+            out.push_str("<mtd>");
+            out.push_str(&mrow(cell));
+            out.push_str("</mtd>");
+        }
+        out.push_str("</mtr>");
+    }
+    out.push_str("</mtable>");
+    out
+}
+
+fn mo_symbol(c: char) -> String {
+    match c {
+        '(' | ')' | '[' | ']' | '|' => format!("<mo stretchy=\"false\">{}</mo>", c),
+        '-' => "<mo>\u{2212}</mo>".to_string(),
+        '*' => "<mo>\u{2217}</mo>".to_string(),
+        '\'' => "<mo>\u{2032}</mo>".to_string(),
+        c => format!("<mo>{}</mo>", escape(&c.to_string())),
+    }
+}
+
+/// Maps ASCII letters and digits to the Mathematical Alphanumeric Symbols block.
+fn styled(variant: Variant, c: char) -> char {
+    // Letters that already existed elsewhere in Unicode are holes in the block.
+    let existing = match (variant, c) {
+        (Variant::Italic, 'h') => Some('\u{210E}'),
+        (Variant::Script, 'B') => Some('\u{212C}'),
+        (Variant::Script, 'E') => Some('\u{2130}'),
+        (Variant::Script, 'F') => Some('\u{2131}'),
+        (Variant::Script, 'H') => Some('\u{210B}'),
+        (Variant::Script, 'I') => Some('\u{2110}'),
+        (Variant::Script, 'L') => Some('\u{2112}'),
+        (Variant::Script, 'M') => Some('\u{2133}'),
+        (Variant::Script, 'R') => Some('\u{211B}'),
+        (Variant::Script, 'e') => Some('\u{212F}'),
+        (Variant::Script, 'g') => Some('\u{210A}'),
+        (Variant::Script, 'o') => Some('\u{2134}'),
+        (Variant::Fraktur, 'C') => Some('\u{212D}'),
+        (Variant::Fraktur, 'H') => Some('\u{210C}'),
+        (Variant::Fraktur, 'I') => Some('\u{2111}'),
+        (Variant::Fraktur, 'R') => Some('\u{211C}'),
+        (Variant::Fraktur, 'Z') => Some('\u{2128}'),
+        (Variant::DoubleStruck, 'C') => Some('\u{2102}'),
+        (Variant::DoubleStruck, 'H') => Some('\u{210D}'),
+        (Variant::DoubleStruck, 'N') => Some('\u{2115}'),
+        (Variant::DoubleStruck, 'P') => Some('\u{2119}'),
+        (Variant::DoubleStruck, 'Q') => Some('\u{211A}'),
+        (Variant::DoubleStruck, 'R') => Some('\u{211D}'),
+        (Variant::DoubleStruck, 'Z') => Some('\u{2124}'),
+        _ => None,
+    };
+    if let Some(c) = existing {
+        return c;
+    }
+
+    let (upper, lower, digits) = match variant {
+        Variant::Normal => return c,
+        Variant::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE)),
+        Variant::Italic => (0x1D434, 0x1D44E, None),
+        Variant::BoldItalic => (0x1D468, 0x1D482, None),
+        Variant::Script => (0x1D49C, 0x1D4B6, None),
+        Variant::Fraktur => (0x1D504, 0x1D51E, None),
+        Variant::DoubleStruck => (0x1D538, 0x1D552, Some(0x1D7D8)),
+        Variant::SansSerif => (0x1D5A0, 0x1D5BA, Some(0x1D7E2)),
+        Variant::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6)),
+    };
+    let code = match c {
+        'A'..='Z' => upper + (c as u32 - 'A' as u32),
+        'a'..='z' => lower + (c as u32 - 'a' as u32),
+        '0'..='9' => match digits {
+            Some(zero) => zero + (c as u32 - '0' as u32),
+            None => return c,
+        },
+        _ => return c,
+    };
+    char::from_u32(code).unwrap_or(c)
+}
+
+fn font_variant(name: &str) -> Option<Variant> {
+    Some(match name {
+        "mathrm" | "rm" | "mathup" => Variant::Normal,
+        "mathbf" | "bf" => Variant::Bold,
+        "mathit" | "it" => Variant::Italic,
+        "boldsymbol" | "bm" => Variant::BoldItalic,
+        "mathcal" | "mathscr" | "cal" => Variant::Script,
+        "mathfrak" | "frak" => Variant::Fraktur,
+        "mathbb" | "Bbb" => Variant::DoubleStruck,
+        "mathsf" | "sf" => Variant::SansSerif,
+        "mathtt" | "tt" => Variant::Monospace,
+        _ => return None,
+    })
+}
+
+fn greek(name: &str) -> Option<char> {
+    Some(match name {
+        "alpha" => 'α',
+        "beta" => 'β',
+        "gamma" => 'γ',
+        "delta" => 'δ',
+        "epsilon" => 'ϵ',
+        "varepsilon" => 'ε',
+        "zeta" => 'ζ',
+        "eta" => 'η',
+        "theta" => 'θ',
+        "vartheta" => 'ϑ',
+        "iota" => 'ι',
+        "kappa" => 'κ',
+        "lambda" => 'λ',
+        "mu" => 'μ',
+        "nu" => 'ν',
+        "xi" => 'ξ',
+        "omicron" => 'ο',
+        "pi" => 'π',
+        "varpi" => 'ϖ',
+        "rho" => 'ρ',
+        "varrho" => 'ϱ',
+        "sigma" => 'σ',
+        "varsigma" => 'ς',
+        "tau" => 'τ',
+        "upsilon" => 'υ',
+        "phi" => 'ϕ',
+        "varphi" => 'φ',
+        "chi" => 'χ',
+        "psi" => 'ψ',
+        "omega" => 'ω',
+        "Gamma" => 'Γ',
+        "Delta" => 'Δ',
+        "Theta" => 'Θ',
+        "Lambda" => 'Λ',
+        "Xi" => 'Ξ',
+        "Pi" => 'Π',
+        "Sigma" => 'Σ',
+        "Upsilon" => 'Υ',
+        "Phi" => 'Φ',
+        "Psi" => 'Ψ',
+        "Omega" => 'Ω',
+        _ => return None,
+    })
+}
+
+/// Symbols that behave like variables rather than operators.
+fn identifier(name: &str) -> Option<char> {
+    Some(match name {
+        "infty" => '∞',
+        "partial" => '∂',
+        "nabla" => '∇',
+        "emptyset" | "varnothing" => '∅',
+        "hbar" => 'ℏ',
+        "ell" => 'ℓ',
+        "aleph" => 'ℵ',
+        "Re" => 'ℜ',
+        "Im" => 'ℑ',
+        "wp" => '℘',
+        "imath" => 'ı',
+        "jmath" => 'ȷ',
+        "top" => '⊤',
+        "bot" => '⊥',
+        _ => return None,
+    })
+}
+
+fn operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "cdot" => "⋅",
+        "times" => "×",
+        "div" => "÷",
+        "pm" => "±",
+        "mp" => "∓",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "∙",
+        "oplus" => "⊕",
+        "ominus" => "⊖",
+        "otimes" => "⊗",
+        "odot" => "⊙",
+        "cup" => "∪",
+        "cap" => "∩",
+        "setminus" => "∖",
+        "wedge" | "land" => "∧",
+        "vee" | "lor" => "∨",
+        "neg" | "lnot" => "¬",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "ll" => "≪",
+        "gg" => "≫",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "propto" => "∝",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "mid" => "∣",
+        "forall" => "∀",
+        "exists" => "∃",
+        "nexists" => "∄",
+        "angle" => "∠",
+        "to" | "rightarrow" => "→",
+        "gets" | "leftarrow" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" => "⇔",
+        "implies" | "Longrightarrow" => "⟹",
+        "impliedby" | "Longleftarrow" => "⟸",
+        "iff" | "Longleftrightarrow" => "⟺",
+        "longrightarrow" => "⟶",
+        "longleftarrow" => "⟵",
+        "mapsto" => "↦",
+        "uparrow" => "↑",
+        "downarrow" => "↓",
+        "ldots" | "dots" | "dotsc" => "…",
+        "cdots" | "dotsb" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "vert" => "|",
+        "Vert" => "‖",
+        "backslash" => "\\",
+        "colon" => ":",
+        "prime" => "′",
+        "degree" => "°",
+        _ => return None,
+    })
+}
+
+/// Big operators, and whether their scripts become limits in display mode.
+fn large_operator(name: &str) -> Option<(&'static str, bool)> {
+    Some(match name {
+        "sum" => ("∑", true),
+        "prod" => ("∏", true),
+        "coprod" => ("∐", true),
+        "bigcup" => ("⋃", true),
+        "bigcap" => ("⋂", true),
+        "bigvee" => ("⋁", true),
+        "bigwedge" => ("⋀", true),
+        "bigoplus" => ("⨁", true),
+        "bigotimes" => ("⨂", true),
+        "int" => ("∫", false),
+        "iint" => ("∬", false),
+        "iiint" => ("∭", false),
+        "oint" => ("∮", false),
+        _ => return None,
+    })
+}
+
+/// Upright function names, and whether their scripts become limits in display mode.
+fn function(name: &str) -> Option<(&'static str, bool)> {
+    Some(match name {
+        "lim" => ("lim", true),
+        "liminf" => ("lim inf", true),
+        "limsup" => ("lim sup", true),
+        "max" => ("max", true),
+        "min" => ("min", true),
+        "sup" => ("sup", true),
+        "inf" => ("inf", true),
+        "det" => ("det", true),
+        "gcd" => ("gcd", true),
+        "Pr" => ("Pr", true),
+        "argmax" => ("arg max", true),
+        "argmin" => ("arg min", true),
+        "sin" => ("sin", false),
+        "cos" => ("cos", false),
+        "tan" => ("tan", false),
+        "cot" => ("cot", false),
+        "sec" => ("sec", false),
+        "csc" => ("csc", false),
+        "arcsin" => ("arcsin", false),
+        "arccos" => ("arccos", false),
+        "arctan" => ("arctan", false),
+        "sinh" => ("sinh", false),
+        "cosh" => ("cosh", false),
+        "tanh" => ("tanh", false),
+        "log" => ("log", false),
+        "ln" => ("ln", false),
+        "lg" => ("lg", false),
+        "exp" => ("exp", false),
+        "dim" => ("dim", false),
+        "ker" => ("ker", false),
+        "deg" => ("deg", false),
+        "hom" => ("hom", false),
+        "arg" => ("arg", false),
+        _ => return None,
+    })
+}
+
+/// Accent character, and whether it should stretch over the whole base.
+fn accent(name: &str) -> Option<(char, bool)> {
+    Some(match name {
+        "hat" => ('^', false),
+        "widehat" => ('^', true),
+        "tilde" => ('~', false),
+        "widetilde" => ('~', true),
+        "bar" => ('\u{AF}', false),
+        "vec" => ('\u{2192}', false),
+        "overrightarrow" => ('\u{2192}', true),
+        "overleftarrow" => ('\u{2190}', true),
+        "dot" => ('\u{2D9}', false),
+        "ddot" => ('\u{A8}', false),
+        "acute" => ('\u{B4}', false),
+        "grave" => ('`', false),
+        "breve" => ('\u{2D8}', false),
+        "check" => ('\u{2C7}', false),
+        _ => return None,
+    })
+}
+
+fn space(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "," | "thinspace" => "0.1667em",
+        ":" | ">" | "medspace" => "0.2222em",
+        ";" | "thickspace" => "0.2778em",
+        "!" | "negthinspace" => "-0.1667em",
+        " " => "0.3333em",
+        "quad" => "1em",
+        "qquad" => "2em",
+        _ => return None,
+    })
+}
+
+fn big_size(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "big" | "bigl" | "bigr" | "bigm" => "1.2em",
+        "Big" | "Bigl" | "Bigr" | "Bigm" => "1.623em",
+        "bigg" | "biggl" | "biggr" | "biggm" => "2.047em",
+        "Bigg" | "Biggl" | "Biggr" | "Biggm" => "2.470em",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_mathml() {
+        assert_eq!(
+            to_mathml("x^2 + \\frac{1}{2}", false).unwrap(),
+            "<math display=\"inline\"><mrow><msup><mi>x</mi><mn>2</mn></msup><mo>+</mo>\
+             <mfrac><mn>1</mn><mn>2</mn></mfrac></mrow></math>"
+        );
+        assert_eq!(
+            to_mathml("\\sum_{i=1}^n i", true).unwrap(),
+            "<math display=\"block\"><mrow><munderover><mo>∑</mo>\
+             <mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></munderover><mi>i</mi></mrow></math>"
+        );
+        assert_eq!(
+            to_mathml("\\mathbb{R}^{3.5}", false).unwrap(),
+            "<math display=\"inline\"><msup><mi>ℝ</mi><mn>3.5</mn></msup></math>"
+        );
+        assert!(
+            to_mathml("\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}", true)
+                .unwrap()
+                .contains("<mtable><mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>")
+        );
+        assert!(
+            to_mathml("\\left( \\text{if } x < y \\right)", false)
+                .unwrap()
+                .contains("<mtext>if </mtext><mi>x</mi><mo>&lt;</mo>")
+        );
+    }
+
+    #[test]
+    fn test_render_math_reports_unsupported_macros() {
+        let mut nodes = vec![ContentNode::Paragraph {
+            children: vec![
+                ContentNode::Math {
+                    value: "\\alpha".to_string(),
+                    display: false,
+                    mathml: None,
+                },
+                ContentNode::Math {
+                    value: "\\foo{x}".to_string(),
+                    display: false,
+                    mathml: None,
+                },
+            ],
+        }];
+
+        let failures = render_math(&mut nodes);
+        assert_eq!(
+            failures,
+            [(
+                "\\foo{x}".to_string(),
+                MathError::UnsupportedMacro("foo".to_string())
+            )]
+        );
+        assert_eq!(failures[0].1.to_string(), "unsupported macro `\\foo`");
+        let ContentNode::Paragraph { children } = &nodes[0] else {
+            unreachable!()
+        };
+        assert!(
+            matches!(&children[0], ContentNode::Math { mathml: Some(m), .. } if m.contains("α"))
+        );
+        assert!(matches!(
+            &children[1],
+            ContentNode::Math { mathml: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_malformed_input_is_an_error() {
+        let deep_groups = format!("{}x{}", "{".repeat(10_000), "}".repeat(10_000));
+        let deep_fracs = "\\frac{".repeat(5_000);
+        let deep_scripts = "x^{".repeat(5_000) + &"}".repeat(5_000);
+        let deep_fences = "\\left(".repeat(5_000);
+        for tex in [
+            "{",
+            "}",
+            "{x",
+            "x}}",
+            "\\frac",
+            "\\frac{1}",
+            "\\frac{}{",
+            "\\sqrt[3",
+            "x^",
+            "x_^",
+            "^^",
+            "\\left(",
+            "\\right)",
+            "\\left( x \\middle",
+            "\\begin{pmatrix} a & b",
+            "\\begin{pmatrix} a \\end{bmatrix}",
+            "\\begin{",
+            "\\end{matrix}",
+            "\\text{abc",
+            "\\mathbb",
+            "\\big",
+            deep_groups.as_str(),
+            deep_fracs.as_str(),
+            deep_scripts.as_str(),
+            deep_fences.as_str(),
+        ] {
+            assert!(to_mathml(tex, false).is_err(), "accepted {:?}", tex);
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_input_does_not_panic() {
+        const PIECES: &[&str] = &[
+            "{",
+            "}",
+            "[",
+            "]",
+            "^",
+            "_",
+            "&",
+            "\\\\",
+            "\\",
+            "x",
+            "1",
+            "2.5",
+            ".",
+            "é",
+            "%",
+            " ",
+            "\\frac",
+            "\\sqrt",
+            "\\left",
+            "\\right",
+            "\\middle",
+            "(",
+            ")",
+            "|",
+            "\\begin{matrix}",
+            "\\end{matrix}",
+            "\\begin{array}",
+            "\\text",
+            "\\mathbf",
+            "\\hat",
+            "\\big",
+            "\\sum",
+            "\\alpha",
+            "\\operatorname",
+            "\\foo",
+            "\\,",
+        ];
+        // A fixed-seed xorshift keeps failures reproducible.
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..20_000 {
+            let len = (next() % 24) as usize;
+            let tex: String = (0..len)
+                .map(|_| PIECES[(next() % PIECES.len() as u64) as usize])
+                .collect();
+            for display in [false, true] {
+                let _ = to_mathml(&tex, display);
+            }
+        }
+    }
+}
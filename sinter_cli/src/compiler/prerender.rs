@@ -0,0 +1,412 @@
+use super::html::{escape, render_html};
+use super::{SiteConfig, post_route};
+use anyhow::{Context, Result};
+use sinter_core::{Post, PostMetadata, TocEntry, page_route};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Id of the element holding the prerendered markup. `sinter_web` removes it
+/// before mounting the app.
+const PRERENDER_ROOT_ID: &str = "sinter-prerender";
+
+// Regions owned by the prerenderer. They are stripped from the shell first, so
+// prerendering into a directory that already has prerendered pages is safe.
+const HEAD_START: &str = "<!--sinter:head-->";
+const HEAD_END: &str = "<!--/sinter:head-->";
+const BODY_START: &str = "<!--sinter:body-->";
+const BODY_END: &str = "<!--/sinter:body-->";
+
+/// The app shell built by trunk, with the prerenderer's own regions removed.
+pub struct Shell {
+    html: String,
+}
+
+impl Shell {
+    pub fn load(web_root: &Path) -> Result<Self> {
+        let path = web_root.join("index.html");
+        let html = fs::read_to_string(&path).with_context(|| {
+            format!(
+                "Failed to read app shell {:?}; build the web app (`trunk build`) first",
+                path
+            )
+        })?;
+        Ok(Self::new(&html))
+    }
+
+    /// The shell as trunk built it, minus its `<title>`.
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+
+    fn new(html: &str) -> Self {
+        let html = strip_region(html, HEAD_START, HEAD_END);
+        let html = strip_region(&html, BODY_START, BODY_END);
+        let html = strip_region(&html, "<title>", "</title>");
+        Self { html }
+    }
+
+    /// Inserts `head` before `</head>`.
+    fn render_head(&self, head: &str) -> String {
+        let mut html = self.html.clone();
+        if let Some(at) = html.find("</head>") {
+            html.insert_str(at, &format!("{}{}{}", HEAD_START, head, HEAD_END));
+        }
+        html
+    }
+
+    /// Inserts `head` before `</head>` and `body` right after the opening `<body>` tag.
+    fn render(&self, head: &str, body: &str) -> String {
+        let mut html = self.render_head(head);
+        if let Some(start) = html.find("<body") {
+            let at = html[start..]
+                .find('>')
+                .map_or(html.len(), |end| start + end + 1);
+            html.insert_str(
+                at,
+                &format!(
+                    "{}<div id=\"{}\">{}</div>{}",
+                    BODY_START, PRERENDER_ROOT_ID, body, BODY_END
+                ),
+            );
+        }
+        html
+    }
+}
+
+fn strip_region(html: &str, start: &str, end: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(from) = rest.find(start) {
+        let Some(to) = rest[from..].find(end) else {
+            break;
+        };
+        out.push_str(&rest[..from]);
+        rest = &rest[from + to + end.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `<title>` and meta tags of one page.
+struct PageMeta<'a> {
+    title: String,
+    description: &'a str,
+    route: &'a str,
+    og_type: &'a str,
+}
+
+/// Writes a static HTML page for every post, archived post and listing page
+/// into `output_dir`, mirroring the client routes. Both lists must already be
+/// sorted newest first. `extra_head` is added to the head of every page.
+/// Returns the number of pages written.
+pub fn write_pages(
+    posts: &[(Post, String)],
+    archives: &[(Post, String)],
+    config: &SiteConfig,
+    posts_per_page: usize,
+    extra_head: &str,
+    shell: &Shell,
+    output_dir: &Path,
+) -> Result<usize> {
+    let renderer = Prerenderer::new(config, extra_head, shell);
+    let mut written = 0;
+
+    for (post, path) in posts.iter().chain(archives) {
+        let route = post_route(path);
+        let html = renderer.post_page(post, &route);
+        write_route(output_dir, &route, &html)?;
+        written += 1;
+    }
+
+    for (base, list, heading) in [
+        ("/", posts, None),
+        ("/archives", archives, Some("Archives")),
+    ] {
+        let chunks: Vec<_> = list.chunks(posts_per_page.max(1)).collect();
+        let total = chunks.len().max(1);
+        for page in 1..=total {
+            let items = chunks.get(page - 1).copied().unwrap_or_default();
+            let route = page_route(base, page);
+            let html = renderer.list_page(heading, items, base, page, total, &route);
+            write_route(output_dir, &route, &html)?;
+            written += 1;
+        }
+    }
+
+    info!("Prerendered {} page(s) into {:?}", written, output_dir);
+    Ok(written)
+}
+
+/// Writes only the app entry point, `index.html`, with the site's title and
+/// meta tags plus `extra_head`, leaving the body to the app.
+pub fn write_index(
+    config: &SiteConfig,
+    extra_head: &str,
+    shell: &Shell,
+    output_dir: &Path,
+) -> Result<()> {
+    let renderer = Prerenderer::new(config, extra_head, shell);
+    let head = renderer.head(&PageMeta {
+        title: config.site.title.clone(),
+        description: &config.site.description,
+        route: "/",
+        og_type: "website",
+    });
+    write_route(output_dir, "/", &shell.render_head(&head))
+}
+
+fn write_route(output_dir: &Path, route: &str, html: &str) -> Result<()> {
+    let dir = output_dir.join(route.trim_start_matches('/'));
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    fs::write(dir.join("index.html"), html)
+        .with_context(|| format!("Failed to write prerendered page for {}", route))
+}
+
+struct Prerenderer<'a> {
+    config: &'a SiteConfig,
+    extra_head: &'a str,
+    shell: &'a Shell,
+    base_url: Option<&'a str>,
+}
+
+impl<'a> Prerenderer<'a> {
+    fn new(config: &'a SiteConfig, extra_head: &'a str, shell: &'a Shell) -> Self {
+        Prerenderer {
+            config,
+            extra_head,
+            shell,
+            base_url: config.base_url().map(|url| url.trim_end_matches('/')),
+        }
+    }
+
+    fn post_page(&self, post: &Post, route: &str) -> String {
+        let meta = &post.metadata;
+        let mut body = String::new();
+        self.site_header(&mut body);
+        body.push_str("<main><article>");
+        let _ = write!(body, "<h1>{}</h1>", escape(&meta.title));
+        post_info(meta, &mut body);
+        if !post.toc.is_empty() {
+            body.push_str("<nav class=\"toc\">");
+            toc_list(&post.toc, &mut body);
+            body.push_str("</nav>");
+        }
+        body.push_str(&render_html(&post.content_ast, None));
+        body.push_str("</article></main>");
+
+        let head = self.head(&PageMeta {
+            title: format!("{} - {}", meta.title, self.config.site.title),
+            description: &meta.summary,
+            route,
+            og_type: "article",
+        });
+        self.shell.render(&head, &body)
+    }
+
+    fn list_page(
+        &self,
+        heading: Option<&str>,
+        items: &[(Post, String)],
+        base: &str,
+        page: usize,
+        total: usize,
+        route: &str,
+    ) -> String {
+        let mut body = String::new();
+        self.site_header(&mut body);
+        body.push_str("<main>");
+        if let Some(heading) = heading {
+            let _ = write!(body, "<h1>{}</h1>", escape(heading));
+        }
+        for (post, path) in items {
+            let meta = &post.metadata;
+            let _ = write!(
+                body,
+                "<article><h2><a href=\"{}\">{}</a></h2>",
+                escape(&post_route(path)),
+                escape(&meta.title)
+            );
+            post_info(meta, &mut body);
+            if !meta.summary.is_empty() {
+                let _ = write!(body, "<p>{}</p>", escape(&meta.summary));
+            }
+            body.push_str("</article>");
+        }
+        if total > 1 {
+            body.push_str("<nav>");
+            if page > 1 {
+                let _ = write!(
+                    body,
+                    "<a rel=\"prev\" href=\"{}\">Previous</a> ",
+                    escape(&page_route(base, page - 1))
+                );
+            }
+            let _ = write!(body, "<span>{} / {}</span>", page, total);
+            if page < total {
+                let _ = write!(
+                    body,
+                    " <a rel=\"next\" href=\"{}\">Next</a>",
+                    escape(&page_route(base, page + 1))
+                );
+            }
+            body.push_str("</nav>");
+        }
+        body.push_str("</main>");
+
+        let site = &self.config.site;
+        let mut title = match heading {
+            Some(heading) => format!("{} - {}", heading, site.title),
+            None => site.title.clone(),
+        };
+        if page > 1 {
+            let _ = write!(title, " ({}/{})", page, total);
+        }
+        let head = self.head(&PageMeta {
+            title,
+            description: &site.description,
+            route,
+            og_type: "website",
+        });
+        self.shell.render(&head, &body)
+    }
+
+    fn site_header(&self, out: &mut String) {
+        let site = &self.config.site;
+        let _ = write!(out, "<header><a href=\"/\">{}</a>", escape(&site.title));
+        if !site.subtitle.is_empty() {
+            let _ = write!(out, "<p>{}</p>", escape(&site.subtitle));
+        }
+        out.push_str(
+            "<nav><a href=\"/\">Home</a> <a href=\"/archives\">Archives</a></nav></header>",
+        );
+    }
+
+    fn head(&self, meta: &PageMeta) -> String {
+        let mut head = String::new();
+        let title = escape(&meta.title);
+        let description = escape(meta.description);
+        let _ = write!(head, "<title>{}</title>", title);
+        let _ = write!(
+            head,
+            "<meta name=\"description\" content=\"{}\">",
+            description
+        );
+        let _ = write!(head, "<meta property=\"og:title\" content=\"{}\">", title);
+        let _ = write!(
+            head,
+            "<meta property=\"og:description\" content=\"{}\">",
+            description
+        );
+        let _ = write!(
+            head,
+            "<meta property=\"og:type\" content=\"{}\">",
+            meta.og_type
+        );
+        let _ = write!(
+            head,
+            "<meta property=\"og:site_name\" content=\"{}\">",
+            escape(&self.config.site.title)
+        );
+        if let Some(base_url) = self.base_url {
+            let url = escape(&format!("{}{}", base_url, meta.route));
+            let _ = write!(head, "<link rel=\"canonical\" href=\"{}\">", url);
+            let _ = write!(head, "<meta property=\"og:url\" content=\"{}\">", url);
+        }
+        head.push_str(self.extra_head);
+        head
+    }
+}
+
+fn post_info(meta: &PostMetadata, out: &mut String) {
+    let date = meta.date.to_string();
+    let _ = write!(out, "<p><time datetime=\"{0}\">{0}</time>", date);
+    for (tag, slug) in meta.tags_with_slugs() {
+        let _ = write!(
+            out,
+            " <a rel=\"tag\" href=\"/tags/{}\">#{}</a>",
+            escape(&slug),
+            escape(tag)
+        );
+    }
+    out.push_str("</p>");
+}
+
+fn toc_list(entries: &[TocEntry], out: &mut String) {
+    out.push_str("<ol>");
+    for entry in entries {
+        let _ = write!(
+            out,
+            "<li><a href=\"#{}\">{}</a>",
+            escape(&entry.id),
+            escape(&entry.title)
+        );
+        if !entry.children.is_empty() {
+            toc_list(&entry.children, out);
+        }
+        out.push_str("</li>");
+    }
+    out.push_str("</ol>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_post;
+
+    #[test]
+    fn test_write_pages() {
+        let config: SiteConfig = toml::from_str(
+            "[site]\ntitle = \"Site\"\nsubtitle = \"\"\ndescription = \"D\"\n[home]\n[feed]\nsite_url = \"https://example.com/\"\n",
+        )
+        .unwrap();
+        let post = |slug: &str| {
+            parse_post(&format!(
+                "---\nid: \"{0}\"\ntitle: \"{0} & co\"\nslug: \"{0}\"\ndate: \"2024-01-01\"\ntags: [\"Rust\"]\nsummary: \"s\"\n---\n## Intro\nHello *world*\n",
+                slug
+            ),
+            &Default::default(),
+        )
+            .unwrap()
+        };
+        let posts = vec![
+            (post("a"), "posts/a.json".to_string()),
+            (post("b"), "posts/b.json".to_string()),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        // A shell that already holds prerendered output must not accumulate it.
+        let shell = Shell::new(&Shell::new(
+            "<html><head><title>Sinter</title><script src=\"/app.js\"></script></head><body></body></html>",
+        ).render("<title>old</title>", "old"));
+
+        let feeds = "<link rel=\"alternate\" href=\"/sinter_data/feed.xml\">";
+        let written = write_pages(&posts, &[], &config, 1, feeds, &shell, dir.path()).unwrap();
+        assert_eq!(written, 5);
+
+        let page = fs::read_to_string(dir.path().join("posts/a/index.html")).unwrap();
+        assert!(page.contains("<title>a &amp; co - Site</title>"));
+        assert!(page.contains("<link rel=\"canonical\" href=\"https://example.com/posts/a\">"));
+        assert!(page.contains("<p>Hello <em>world</em></p>"));
+        assert!(
+            page.contains(
+                "<nav class=\"toc\"><ol><li><a href=\"#intro\">Intro</a></li></ol></nav>"
+            )
+        );
+        assert!(page.contains("<h2 id=\"intro\">Intro</h2>"));
+        assert!(page.contains("<script src=\"/app.js\"></script>"));
+        assert!(!page.contains("old") && !page.contains("Sinter"));
+        assert!(page.contains(feeds));
+
+        let second = fs::read_to_string(dir.path().join("page/2/index.html")).unwrap();
+        assert!(second.contains("<a href=\"/posts/b\">b &amp; co</a>"));
+        assert!(second.contains("<a rel=\"prev\" href=\"/\">"));
+        assert!(dir.path().join("archives/index.html").is_file());
+
+        let index_dir = tempfile::tempdir().unwrap();
+        write_index(&config, feeds, &shell, index_dir.path()).unwrap();
+        let index = fs::read_to_string(index_dir.path().join("index.html")).unwrap();
+        assert!(index.contains("<title>Site</title>") && index.contains(feeds));
+        assert!(index.ends_with("</head><body></body></html>"));
+    }
+}
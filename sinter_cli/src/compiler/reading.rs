@@ -0,0 +1,144 @@
+//! Word count and reading time of a post body.
+//!
+//! Space-separated text is counted in words and CJK text in characters, since
+//! it has no word boundaries. Code, math and raw HTML are not counted.
+
+use super::ReadingSection;
+use sinter_core::ContentNode;
+use sinter_core::search::is_cjk;
+
+/// Amount of readable text in a post.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Length {
+    words: usize,
+    cjk_chars: usize,
+}
+
+impl Length {
+    /// Total word count, with each CJK character counted as one word.
+    pub fn words(self) -> usize {
+        self.words + self.cjk_chars
+    }
+
+    /// Estimated reading time in whole minutes, rounded up. Empty posts take
+    /// zero minutes; anything else at least one.
+    pub fn minutes(self, reading: &ReadingSection) -> u32 {
+        let minutes = self.words as f64 / reading.words_per_minute.max(1) as f64
+            + self.cjk_chars as f64 / reading.cjk_chars_per_minute.max(1) as f64;
+        minutes.ceil() as u32
+    }
+
+    fn add_text(&mut self, text: &str) {
+        let mut in_word = false;
+        for c in text.chars() {
+            if is_cjk(c) {
+                self.cjk_chars += 1;
+                in_word = false;
+            } else if c.is_whitespace() {
+                in_word = false;
+            } else if c.is_alphanumeric() && !in_word {
+                // Punctuation inside a word (`don't`, `co-op`) does not split it.
+                self.words += 1;
+                in_word = true;
+            }
+        }
+    }
+}
+
+/// Measures the prose of `nodes`. Inline nodes are joined as-is so formatting
+/// never splits a word; block nodes end with a line break.
+pub fn measure(nodes: &[ContentNode]) -> Length {
+    fn walk(node: &ContentNode, out: &mut String) {
+        match node {
+            ContentNode::Text { value } | ContentNode::InlineCode { value } => out.push_str(value),
+            ContentNode::Image { alt, .. } => out.push_str(alt),
+            ContentNode::Emphasis { children }
+            | ContentNode::Strong { children }
+            | ContentNode::Strikethrough { children }
+            | ContentNode::Link { children, .. } => {
+                children.iter().for_each(|child| walk(child, out));
+            }
+            ContentNode::Admonition {
+                title, children, ..
+            } => {
+                out.push_str(title);
+                out.push('\n');
+                children.iter().for_each(|child| walk(child, out));
+                out.push('\n');
+            }
+            ContentNode::Paragraph { children }
+            | ContentNode::Heading { children, .. }
+            | ContentNode::List { children, .. }
+            | ContentNode::ListItem { children }
+            | ContentNode::BlockQuote { children }
+            | ContentNode::FootnoteDefinition { children, .. }
+            | ContentNode::Table { children, .. }
+            | ContentNode::TableHead { children }
+            | ContentNode::TableBody { children }
+            | ContentNode::TableRow { children }
+            | ContentNode::TableCell { children, .. } => {
+                children.iter().for_each(|child| walk(child, out));
+                out.push('\n');
+            }
+            ContentNode::CodeBlock { .. }
+            | ContentNode::Html { .. }
+            | ContentNode::Math { .. }
+            | ContentNode::TaskListMarker { .. }
+            | ContentNode::FootnoteReference { .. }
+            | ContentNode::ThematicBreak => {}
+        }
+    }
+
+    let mut text = String::new();
+    nodes.iter().for_each(|node| walk(node, &mut text));
+    let mut length = Length::default();
+    length.add_text(&text);
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> ContentNode {
+        ContentNode::Text {
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_measure_mixed_text() {
+        let nodes = vec![
+            ContentNode::Paragraph {
+                children: vec![
+                    text("Don't panic, co-"),
+                    ContentNode::Strong {
+                        children: vec![text("op")],
+                    },
+                    text(" mode."),
+                ],
+            },
+            ContentNode::Paragraph {
+                children: vec![text("用 Rust 写静态站点。")],
+            },
+            ContentNode::CodeBlock {
+                lang: None,
+                code_text: "fn main() {}".to_string(),
+                tokens: vec![],
+            },
+        ];
+        let length = measure(&nodes);
+        assert_eq!(length.words(), 4 + 1 + 6);
+
+        let reading = ReadingSection::default();
+        assert_eq!(length.minutes(&reading), 1);
+        assert_eq!(Length::default().minutes(&reading), 0);
+
+        let long = Length {
+            words: 450,
+            cjk_chars: 400,
+        };
+        // 2.25 minutes of words plus 1 minute of CJK text.
+        assert_eq!(long.minutes(&reading), 4);
+    }
+}
@@ -0,0 +1,198 @@
+use super::post_route;
+use anyhow::{Context, Result};
+use sinter_core::constants::SEARCH_INDEX_FILENAME;
+use sinter_core::search::{Posting, SearchDoc, SearchManifest, SearchShard, index_terms, shard_of};
+use sinter_core::{ContentNode, Post};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Occurrences in the title or tags count more than occurrences in the body.
+const TITLE_WEIGHT: u32 = 5;
+const TAG_WEIGHT: u32 = 3;
+const BODY_WEIGHT: u32 = 1;
+
+/// Builds the inverted index over `posts` (`(post, output path)` pairs).
+pub fn build_index(
+    posts: &[&(Post, String)],
+    shard_count: u32,
+    cjk_bigrams: bool,
+) -> (SearchManifest, Vec<SearchShard>) {
+    let shard_count = shard_count.max(1);
+    let mut docs = Vec::with_capacity(posts.len());
+    let mut shards = vec![SearchShard::default(); shard_count as usize];
+
+    for (doc_id, (post, path)) in posts.iter().enumerate() {
+        let metadata = &post.metadata;
+        docs.push(SearchDoc {
+            title: metadata.title.clone(),
+            url: post_route(path),
+            date: metadata.date.clone(),
+            summary: metadata.summary.clone(),
+        });
+
+        let mut weights: BTreeMap<String, u32> = BTreeMap::new();
+        let mut add = |text: &str, weight: u32| {
+            for term in index_terms(text, cjk_bigrams) {
+                *weights.entry(term).or_default() += weight;
+            }
+        };
+        add(&metadata.title, TITLE_WEIGHT);
+        for tag in &metadata.tags {
+            add(tag, TAG_WEIGHT);
+        }
+        add(&metadata.summary, BODY_WEIGHT);
+        add(&plain_text(&post.content_ast), BODY_WEIGHT);
+
+        for (term, weight) in weights {
+            let shard = &mut shards[shard_of(&term, shard_count) as usize];
+            shard
+                .terms
+                .entry(term)
+                .or_default()
+                .push(Posting(doc_id as u32, weight));
+        }
+    }
+
+    let manifest = SearchManifest {
+        shard_count,
+        cjk_bigrams,
+        docs,
+    };
+    (manifest, shards)
+}
+
+/// Writes `index.json` and every `shard_{n}.json` into `output_dir`.
+pub fn write_index(
+    manifest: &SearchManifest,
+    shards: &[SearchShard],
+    output_dir: &Path,
+) -> Result<()> {
+    fs::create_dir_all(output_dir).context("Failed to create search directory")?;
+
+    let manifest_json =
+        serde_json::to_string(manifest).context("Failed to serialize search manifest")?;
+    fs::write(output_dir.join(SEARCH_INDEX_FILENAME), manifest_json)
+        .context("Failed to write search manifest")?;
+
+    let mut terms = 0;
+    for (i, shard) in shards.iter().enumerate() {
+        terms += shard.terms.len();
+        let shard_json =
+            serde_json::to_string(shard).context("Failed to serialize search shard")?;
+        fs::write(output_dir.join(format!("shard_{}.json", i)), shard_json)
+            .context("Failed to write search shard")?;
+    }
+
+    info!(
+        "Search index: {} documents, {} terms in {} shards.",
+        manifest.docs.len(),
+        terms,
+        shards.len()
+    );
+    Ok(())
+}
+
+/// The searchable text of a post body. Inline nodes are concatenated as-is so
+/// formatting never splits a word; block nodes end with a line break.
+fn plain_text(nodes: &[ContentNode]) -> String {
+    fn walk(node: &ContentNode, out: &mut String) {
+        match node {
+            ContentNode::Text { value } | ContentNode::InlineCode { value } => out.push_str(value),
+            ContentNode::CodeBlock { code_text, .. } => {
+                out.push_str(code_text);
+                out.push('\n');
+            }
+            ContentNode::Image { alt, .. } => out.push_str(alt),
+            ContentNode::Emphasis { children }
+            | ContentNode::Strong { children }
+            | ContentNode::Strikethrough { children }
+            | ContentNode::Link { children, .. } => {
+                children.iter().for_each(|child| walk(child, out));
+            }
+            ContentNode::Admonition {
+                title, children, ..
+            } => {
+                out.push_str(title);
+                out.push('\n');
+                children.iter().for_each(|child| walk(child, out));
+                out.push('\n');
+            }
+            ContentNode::Paragraph { children }
+            | ContentNode::Heading { children, .. }
+            | ContentNode::List { children, .. }
+            | ContentNode::ListItem { children }
+            | ContentNode::BlockQuote { children }
+            | ContentNode::FootnoteDefinition { children, .. }
+            | ContentNode::Table { children, .. }
+            | ContentNode::TableHead { children }
+            | ContentNode::TableBody { children }
+            | ContentNode::TableRow { children }
+            | ContentNode::TableCell { children, .. } => {
+                children.iter().for_each(|child| walk(child, out));
+                out.push('\n');
+            }
+            ContentNode::Html { .. }
+            | ContentNode::Math { .. }
+            | ContentNode::TaskListMarker { .. }
+            | ContentNode::FootnoteReference { .. }
+            | ContentNode::ThematicBreak => {}
+        }
+    }
+
+    let mut out = String::new();
+    nodes.iter().for_each(|node| walk(node, &mut out));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_post;
+    use sinter_core::search::{rank, tokenize};
+
+    #[test]
+    fn test_index_round_trip() {
+        let post = parse_post(
+            "---\nid: \"1\"\ntitle: \"编译器\"\nslug: \"c\"\ndate: \"2024-01-01\"\nsummary: \"s\"\n---\n用 **Rust** 写静态站点生成器。\n",
+            &Default::default(),
+        )
+        .unwrap();
+        let entry = (post, "archives/c.json".to_string());
+        let (manifest, shards) = build_index(&[&entry], 4, true);
+        assert_eq!(manifest.docs[0].url, "/archives/posts/c");
+
+        let terms = tokenize("站点 rust", true);
+        let hits = rank(&manifest, &terms, |t| {
+            shards[shard_of(t, manifest.shard_count) as usize]
+                .terms
+                .get(t)
+                .map(Vec::as_slice)
+        });
+        assert_eq!(hits.len(), 1);
+    }
+
+    #[test]
+    fn test_one_character_cjk_query() {
+        let post = parse_post(
+            "---\nid: \"1\"\ntitle: \"防锈\"\nslug: \"r\"\ndate: \"2024-01-01\"\nsummary: \"s\"\n---\n铁会生锈。\n",
+            &Default::default(),
+        )
+        .unwrap();
+        let entry = (post, "posts/r.json".to_string());
+        let (manifest, shards) = build_index(&[&entry], 4, true);
+
+        let lookup = |t: &str| {
+            shards[shard_of(t, manifest.shard_count) as usize]
+                .terms
+                .get(t)
+                .map(Vec::as_slice)
+        };
+        let terms = tokenize("锈", true);
+        assert_eq!(terms, ["锈"]);
+        assert_eq!(rank(&manifest, &terms, lookup).len(), 1);
+        // Longer queries still match by bigram.
+        assert_eq!(rank(&manifest, &tokenize("生锈", true), lookup).len(), 1);
+    }
+}
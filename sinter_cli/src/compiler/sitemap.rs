@@ -0,0 +1,181 @@
+use super::html::escape;
+use super::{SitemapCollection, group_by_tag, newest_first, post_route};
+use anyhow::{Context, Result};
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use sinter_core::{LiteDate, SitePostMetadata, page_route};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+pub const SITEMAP_FILENAME: &str = "sitemap.xml";
+pub const ROBOTS_FILENAME: &str = "robots.txt";
+
+/// Characters escaped in sitemap URL paths; non-ASCII is always escaped.
+const PATH_ESCAPE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+struct UrlEntry {
+    path: String,
+    lastmod: Option<LiteDate>,
+}
+
+/// Writes `sitemap.xml` and a `robots.txt` that points at it into
+/// `output_dir`, which is served as the root of the site. Both inputs must
+/// already be sorted newest first.
+pub fn write_sitemap(
+    posts: &[SitePostMetadata],
+    archives: &[SitePostMetadata],
+    posts_per_page: usize,
+    base_url: &str,
+    exclude: &[SitemapCollection],
+    output_dir: &Path,
+) -> Result<()> {
+    let base_url = base_url.trim_end_matches('/');
+    let included = |collection| !exclude.contains(&collection);
+    let mut entries = Vec::new();
+
+    if included(SitemapCollection::Home) {
+        push_pages(&mut entries, "/", posts, posts_per_page);
+    }
+    if included(SitemapCollection::ArchivePages) {
+        push_pages(&mut entries, "/archives", archives, posts_per_page);
+    }
+    if included(SitemapCollection::Tags) {
+        let mut all: Vec<_> = posts.iter().chain(archives).cloned().collect();
+        all.sort_by(|a, b| newest_first(&a.metadata, &b.metadata));
+        for (slug, (_, tagged)) in group_by_tag(&all) {
+            push_pages(
+                &mut entries,
+                &format!("/tags/{}", slug),
+                &tagged,
+                posts_per_page,
+            );
+        }
+    }
+    for (collection, list) in [
+        (SitemapCollection::Posts, posts),
+        (SitemapCollection::Archives, archives),
+    ] {
+        if included(collection) {
+            entries.extend(list.iter().map(|post| UrlEntry {
+                path: post_route(&post.path),
+                lastmod: Some(post.metadata.date.clone()),
+            }));
+        }
+    }
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for entry in &entries {
+        let loc = format!(
+            "{}{}",
+            base_url,
+            utf8_percent_encode(&entry.path, PATH_ESCAPE)
+        );
+        let _ = write!(xml, "<url><loc>{}</loc>", escape(&loc));
+        if let Some(date) = &entry.lastmod {
+            let _ = write!(xml, "<lastmod>{}</lastmod>", date);
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+
+    fs::write(output_dir.join(SITEMAP_FILENAME), xml).context("Failed to write sitemap")?;
+
+    let robots = format!(
+        "User-agent: *\nAllow: /\n\nSitemap: {}/{}\n",
+        base_url, SITEMAP_FILENAME
+    );
+    fs::write(output_dir.join(ROBOTS_FILENAME), robots).context("Failed to write robots.txt")?;
+
+    info!("Sitemap written with {} URL(s).", entries.len());
+    Ok(())
+}
+
+/// Adds every listing page of `posts` under `base`; a page's `lastmod` is the
+/// date of its newest post.
+fn push_pages(
+    entries: &mut Vec<UrlEntry>,
+    base: &str,
+    posts: &[SitePostMetadata],
+    posts_per_page: usize,
+) {
+    if posts.is_empty() {
+        return;
+    }
+    for (i, chunk) in posts.chunks(posts_per_page).enumerate() {
+        entries.push(UrlEntry {
+            path: page_route(base, i + 1),
+            lastmod: chunk.first().map(|post| post.metadata.date.clone()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sinter_core::PostMetadata;
+
+    fn entry(slug: &str, path: &str, day: u8, tags: &[&str]) -> SitePostMetadata {
+        SitePostMetadata {
+            metadata: PostMetadata {
+                id: slug.to_string(),
+                title: slug.to_string(),
+                slug: slug.to_string(),
+                date: LiteDate {
+                    year: 2024,
+                    month: 1,
+                    day,
+                },
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                tag_slugs: vec![],
+                draft: false,
+                summary: String::new(),
+                word_count: 0,
+                reading_time: 0,
+            },
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_write_sitemap() {
+        let posts = vec![
+            entry("b", "posts/b.json", 2, &["笔记"]),
+            entry("a", "posts/a.json", 1, &[]),
+        ];
+        let archives = vec![entry("old", "archives/old.json", 1, &[])];
+        let dir = tempfile::tempdir().unwrap();
+
+        write_sitemap(
+            &posts,
+            &archives,
+            1,
+            "https://example.com/",
+            &[SitemapCollection::ArchivePages],
+            dir.path(),
+        )
+        .unwrap();
+
+        let xml = fs::read_to_string(dir.path().join(SITEMAP_FILENAME)).unwrap();
+        assert!(xml.contains("<loc>https://example.com/</loc><lastmod>2024-01-02</lastmod>"));
+        assert!(xml.contains("<loc>https://example.com/page/2</loc><lastmod>2024-01-01</lastmod>"));
+        assert!(xml.contains("<loc>https://example.com/tags/%E7%AC%94%E8%AE%B0</loc>"));
+        assert!(xml.contains("<loc>https://example.com/archives/posts/old</loc>"));
+        assert!(!xml.contains("<loc>https://example.com/archives</loc>"));
+
+        let robots = fs::read_to_string(dir.path().join(ROBOTS_FILENAME)).unwrap();
+        assert!(robots.contains("Sitemap: https://example.com/sitemap.xml\n"));
+    }
+}
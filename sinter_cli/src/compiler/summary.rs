@@ -0,0 +1,173 @@
+//! Summaries for posts whose frontmatter has none.
+//!
+//! The summary is the text before a `<!-- more -->` marker or, without one,
+//! the text of the leading paragraphs; headings are left out of both, since
+//! the title already stands above the summary. Either is cut to a length limit at a
+//! word boundary, never inside a grapheme cluster.
+
+use sinter_core::ContentNode;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Generates a plain-text summary of at most `max_chars` characters
+/// (grapheme clusters), plus an ellipsis when the text was cut.
+pub fn summarize(nodes: &[ContentNode], max_chars: usize) -> String {
+    let mut text = String::new();
+    match nodes.iter().position(is_more_marker) {
+        Some(marker) => {
+            for node in &nodes[..marker] {
+                walk(node, &mut text);
+                text.push(' ');
+            }
+        }
+        None => {
+            for node in nodes {
+                if let ContentNode::Paragraph { children } = node {
+                    children.iter().for_each(|child| walk(child, &mut text));
+                    text.push(' ');
+                    if text.len() > max_chars * 4 {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate(&text, max_chars)
+}
+
+fn is_more_marker(node: &ContentNode) -> bool {
+    let ContentNode::Html { value } = node else {
+        return false;
+    };
+    let Some(comment) = value
+        .trim()
+        .strip_prefix("<!--")
+        .and_then(|rest| rest.strip_suffix("-->"))
+    else {
+        return false;
+    };
+    comment.trim().eq_ignore_ascii_case("more")
+}
+
+/// Collects readable text; headings, code, math and raw HTML are left out.
+fn walk(node: &ContentNode, out: &mut String) {
+    match node {
+        ContentNode::Text { value } | ContentNode::InlineCode { value } => out.push_str(value),
+        ContentNode::Emphasis { children }
+        | ContentNode::Strong { children }
+        | ContentNode::Strikethrough { children }
+        | ContentNode::Link { children, .. } => {
+            children.iter().for_each(|child| walk(child, out));
+        }
+        ContentNode::Paragraph { children }
+        | ContentNode::List { children, .. }
+        | ContentNode::ListItem { children }
+        | ContentNode::BlockQuote { children }
+        | ContentNode::Admonition { children, .. }
+        | ContentNode::Table { children, .. }
+        | ContentNode::TableHead { children }
+        | ContentNode::TableBody { children }
+        | ContentNode::TableRow { children }
+        | ContentNode::TableCell { children, .. } => {
+            children.iter().for_each(|child| walk(child, out));
+            out.push(' ');
+        }
+        ContentNode::Heading { .. }
+        | ContentNode::FootnoteDefinition { .. }
+        | ContentNode::CodeBlock { .. }
+        | ContentNode::Html { .. }
+        | ContentNode::Math { .. }
+        | ContentNode::Image { .. }
+        | ContentNode::TaskListMarker { .. }
+        | ContentNode::FootnoteReference { .. }
+        | ContentNode::ThematicBreak => {}
+    }
+}
+
+/// Cuts `text` to `max_chars` grapheme clusters, backing up to the last word
+/// boundary so no word is split. CJK characters are words of their own.
+fn truncate(text: &str, max_chars: usize) -> String {
+    let Some((limit, _)) = text.grapheme_indices(true).nth(max_chars) else {
+        return text.to_string();
+    };
+
+    // A single word longer than the limit is cut between graphemes instead.
+    let cut = text
+        .split_word_bound_indices()
+        .map(|(start, _)| start)
+        .take_while(|&start| start <= limit)
+        .last()
+        .filter(|&start| start > 0)
+        .unwrap_or(limit);
+
+    let kept = text[..cut].trim_end_matches(|c: char| c.is_whitespace() || is_punctuation(c));
+    format!("{}…", kept)
+}
+
+/// ASCII punctuation plus the general, CJK and full-width punctuation blocks,
+/// so a cut never leaves a dangling `，` or `——` before the ellipsis.
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(
+            c,
+            '\u{2010}'..='\u{2027}'
+                | '\u{2030}'..='\u{205E}'
+                | '\u{3000}'..='\u{303F}'
+                | '\u{FE10}'..='\u{FE1F}'
+                | '\u{FE30}'..='\u{FE4F}'
+        )
+        || (matches!(c, '\u{FF01}'..='\u{FF65}') && !c.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraph(text: &str) -> ContentNode {
+        ContentNode::Paragraph {
+            children: vec![ContentNode::Text {
+                value: text.to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let nodes = vec![
+            ContentNode::Heading {
+                level: 2,
+                id: None,
+                classes: vec![],
+                children: vec![ContentNode::Text {
+                    value: "Intro".to_string(),
+                }],
+            },
+            paragraph("Static sites are fast."),
+            paragraph("They are also cheap to host."),
+        ];
+        assert_eq!(
+            summarize(&nodes, 100),
+            "Static sites are fast. They are also cheap to host."
+        );
+        assert_eq!(summarize(&nodes, 28), "Static sites are fast. They…");
+
+        let mut with_marker = nodes.clone();
+        with_marker.insert(
+            2,
+            ContentNode::Html {
+                value: "<!-- more -->\n".to_string(),
+            },
+        );
+        assert_eq!(summarize(&with_marker, 100), "Static sites are fast.");
+
+        // CJK text is cut between characters, combining sequences stay whole.
+        let cjk = vec![paragraph("静态站点生成器，编译期完成所有工作。")];
+        assert_eq!(summarize(&cjk, 6), "静态站点生成…");
+        assert_eq!(summarize(&cjk, 8), "静态站点生成器…");
+        let dash = vec![paragraph("编译期完成——所有工作")];
+        assert_eq!(summarize(&dash, 7), "编译期完成…");
+        let emoji = vec![paragraph("ok 👍🏽👍🏽 done")];
+        assert_eq!(summarize(&emoji, 4), "ok 👍🏽…");
+    }
+}
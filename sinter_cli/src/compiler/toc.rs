@@ -0,0 +1,213 @@
+//! Heading anchors and the table of contents of a post.
+//!
+//! Every `ContentNode::Heading` gets an `id`: explicit `{ #id }` attributes are
+//! kept, the rest are slugified from the heading text and de-duplicated with a
+//! `-1`, `-2`, ... suffix; headings with no usable text become `section-N`.
+//! Top-level headings are then folded into a nested outline so themes can
+//! render a TOC without walking the AST.
+
+use sinter_core::{ContentNode, TocEntry, slugify};
+use std::collections::HashSet;
+
+/// Fills in missing heading ids and returns the post's table of contents.
+pub fn assign_heading_ids(nodes: &mut [ContentNode]) -> Vec<TocEntry> {
+    // Ids written by the author or produced for footnotes win over generated ones.
+    let mut taken = HashSet::new();
+    reserve_ids(nodes, &mut taken);
+    generate_ids(nodes, &mut taken);
+
+    let mut toc = Vec::new();
+    for node in nodes.iter() {
+        if let ContentNode::Heading {
+            level,
+            id: Some(id),
+            children,
+            ..
+        } = node
+        {
+            let entry = TocEntry {
+                level: *level,
+                id: id.clone(),
+                title: heading_text(children),
+                children: Vec::new(),
+            };
+            insert_entry(&mut toc, entry);
+        }
+    }
+    toc
+}
+
+fn reserve_ids(nodes: &[ContentNode], taken: &mut HashSet<String>) {
+    for node in nodes {
+        match node {
+            ContentNode::Heading { id: Some(id), .. } => {
+                taken.insert(id.clone());
+            }
+            ContentNode::FootnoteDefinition { id, back_refs, .. } => {
+                taken.insert(id.clone());
+                taken.extend(back_refs.iter().cloned());
+            }
+            _ => {}
+        }
+        if let Some(children) = block_children(node) {
+            reserve_ids(children, taken);
+        }
+    }
+}
+
+fn generate_ids(nodes: &mut [ContentNode], taken: &mut HashSet<String>) {
+    for node in nodes.iter_mut() {
+        if let ContentNode::Heading {
+            id: id @ None,
+            children,
+            ..
+        } = node
+        {
+            let mut base = slugify(&heading_text(children));
+            let mut n = 0;
+            if base.is_empty() {
+                // Headings without any sluggable text are numbered instead.
+                base = "section".to_string();
+                n = 1;
+            }
+            let mut candidate = if n == 0 {
+                base.clone()
+            } else {
+                format!("{}-{}", base, n)
+            };
+            while taken.contains(&candidate) {
+                n += 1;
+                candidate = format!("{}-{}", base, n);
+            }
+            taken.insert(candidate.clone());
+            *id = Some(candidate);
+        } else if let Some(children) = block_children_mut(node) {
+            generate_ids(children, taken);
+        }
+    }
+}
+
+/// Appends `entry` under the last entry of a lower level, or at this level.
+fn insert_entry(entries: &mut Vec<TocEntry>, entry: TocEntry) {
+    match entries.last_mut() {
+        Some(last) if last.level < entry.level => insert_entry(&mut last.children, entry),
+        _ => entries.push(entry),
+    }
+}
+
+/// Block containers that may hold headings.
+fn block_children(node: &ContentNode) -> Option<&Vec<ContentNode>> {
+    match node {
+        ContentNode::List { children, .. }
+        | ContentNode::ListItem { children }
+        | ContentNode::BlockQuote { children }
+        | ContentNode::Admonition { children, .. }
+        | ContentNode::FootnoteDefinition { children, .. } => Some(children),
+        _ => None,
+    }
+}
+
+fn block_children_mut(node: &mut ContentNode) -> Option<&mut Vec<ContentNode>> {
+    match node {
+        ContentNode::List { children, .. }
+        | ContentNode::ListItem { children }
+        | ContentNode::BlockQuote { children }
+        | ContentNode::Admonition { children, .. }
+        | ContentNode::FootnoteDefinition { children, .. } => Some(children),
+        _ => None,
+    }
+}
+
+/// The visible text of a heading, with inline formatting flattened.
+fn heading_text(nodes: &[ContentNode]) -> String {
+    fn walk(node: &ContentNode, out: &mut String) {
+        match node {
+            ContentNode::Text { value }
+            | ContentNode::InlineCode { value }
+            | ContentNode::Math { value, .. } => out.push_str(value),
+            ContentNode::Image { alt, .. } => out.push_str(alt),
+            ContentNode::Emphasis { children }
+            | ContentNode::Strong { children }
+            | ContentNode::Strikethrough { children }
+            | ContentNode::Link { children, .. } => {
+                children.iter().for_each(|child| walk(child, out));
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = String::new();
+    nodes.iter().for_each(|node| walk(node, &mut out));
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(level: u8, id: Option<&str>, text: &str) -> ContentNode {
+        ContentNode::Heading {
+            level,
+            id: id.map(str::to_string),
+            classes: vec![],
+            children: vec![ContentNode::Text {
+                value: text.to_string(),
+            }],
+        }
+    }
+
+    fn id_of(node: &ContentNode) -> &str {
+        match node {
+            ContentNode::Heading { id: Some(id), .. } => id,
+            other => panic!("Expected heading with id, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ids_and_toc() {
+        let mut nodes = vec![
+            heading(2, None, "Getting Started"),
+            heading(3, None, "安装 步骤"),
+            heading(3, Some("usage"), "Usage"),
+            heading(2, None, "Getting Started"),
+            heading(4, None, "Usage"),
+            heading(2, None, "FAQ"),
+            heading(2, None, ""),
+            heading(2, Some("section-2"), "Taken"),
+            heading(2, None, " "),
+            ContentNode::BlockQuote {
+                children: vec![heading(2, None, "faq")],
+            },
+        ];
+        let toc = assign_heading_ids(&mut nodes);
+
+        let ids: Vec<&str> = nodes[..9].iter().map(id_of).collect();
+        assert_eq!(
+            ids,
+            [
+                "getting-started",
+                "安装-步骤",
+                "usage",
+                "getting-started-1",
+                "usage-1",
+                "faq",
+                "section-1",
+                "section-2",
+                "section-3"
+            ]
+        );
+        match &nodes[9] {
+            ContentNode::BlockQuote { children } => assert_eq!(id_of(&children[0]), "faq-1"),
+            other => panic!("Expected BlockQuote, got {:?}", other),
+        }
+
+        // Quoted headings get anchors but stay out of the outline.
+        assert_eq!(toc.len(), 6);
+        assert_eq!(toc[0].title, "Getting Started");
+        let nested: Vec<&str> = toc[0].children.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(nested, ["安装-步骤", "usage"]);
+        assert_eq!(toc[1].children[0].id, "usage-1");
+        assert_eq!(toc[1].children[0].level, 4);
+        assert!(toc[2].children.is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+//! Precompressed `.gz` and `.br` siblings for static hosts that serve them
+//! directly (nginx `gzip_static`/`brotli_static`, Caddy `precompressed`).
+
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use rayon::prelude::*;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Brotli quality and window size; the files are compressed once per build,
+/// so the slowest, smallest settings are affordable.
+const BROTLI_QUALITY: u32 = 11;
+const BROTLI_WINDOW: u32 = 22;
+
+/// Total sizes of a batch of compressed files, for the build report.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Savings {
+    pub files: usize,
+    pub original: u64,
+    pub gzip: u64,
+    pub brotli: u64,
+}
+
+impl Savings {
+    fn add(self, other: Savings) -> Savings {
+        Savings {
+            files: self.files + other.files,
+            original: self.original + other.original,
+            gzip: self.gzip + other.gzip,
+            brotli: self.brotli + other.brotli,
+        }
+    }
+}
+
+impl fmt::Display for Savings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |size: u64| {
+            if self.original == 0 {
+                0.0
+            } else {
+                100.0 * (1.0 - size as f64 / self.original as f64)
+            }
+        };
+        write!(
+            f,
+            "{} file(s), {} -> gzip {} (-{:.1}%), brotli {} (-{:.1}%)",
+            self.files,
+            kib(self.original),
+            kib(self.gzip),
+            percent(self.gzip),
+            kib(self.brotli),
+            percent(self.brotli)
+        )
+    }
+}
+
+fn kib(bytes: u64) -> String {
+    format!("{:.1} KiB", bytes as f64 / 1024.0)
+}
+
+/// Compresses every file below `dir` whose extension is in `extensions`.
+pub fn compress_dir(dir: &Path, extensions: &[&str]) -> Result<Savings> {
+    let files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| extensions.iter().any(|wanted| ext == *wanted))
+        })
+        .collect();
+    compress_files(&files)
+}
+
+/// Writes `<file>.gz` and `<file>.br` next to each of `files`, in parallel.
+pub fn compress_files(files: &[PathBuf]) -> Result<Savings> {
+    files
+        .par_iter()
+        .map(|path| compress_file(path))
+        .try_reduce(Savings::default, |a, b| Ok(a.add(b)))
+}
+
+fn compress_file(path: &Path) -> Result<Savings> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+
+    // The gzip header carries no name or timestamp, so unchanged input gives
+    // byte-identical output and deploys leave the file alone.
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::best());
+    gzip.write_all(&bytes)?;
+    let gzip = gzip.finish()?;
+
+    let mut brotli = Vec::new();
+    {
+        let mut writer =
+            brotli::CompressorWriter::new(&mut brotli, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+        writer.write_all(&bytes)?;
+    }
+
+    for (extension, data) in [("gz", &gzip), ("br", &brotli)] {
+        let mut target = path.as_os_str().to_owned();
+        target.push(".");
+        target.push(extension);
+        fs::write(&target, data).with_context(|| format!("Failed to write {:?}", target))?;
+    }
+
+    Ok(Savings {
+        files: 1,
+        original: bytes.len() as u64,
+        gzip: gzip.len() as u64,
+        brotli: brotli.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_compress_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = "{\"posts\":[".to_string() + &"{\"title\":\"Hello\"},".repeat(200) + "{}]}";
+        fs::create_dir_all(dir.path().join("pages")).unwrap();
+        fs::write(dir.path().join("pages/page_1.json"), &json).unwrap();
+        fs::write(dir.path().join("robots.txt"), "User-agent: *").unwrap();
+
+        let savings = compress_dir(dir.path(), &["json"]).unwrap();
+        assert_eq!(savings.files, 1);
+        assert_eq!(savings.original, json.len() as u64);
+        assert!(savings.gzip < savings.original / 10);
+        assert!(savings.brotli < savings.original / 10);
+        assert!(!dir.path().join("robots.txt.gz").exists());
+
+        let mut unzipped = String::new();
+        let gz = fs::read(dir.path().join("pages/page_1.json.gz")).unwrap();
+        flate2::read::GzDecoder::new(&gz[..])
+            .read_to_string(&mut unzipped)
+            .unwrap();
+        assert_eq!(unzipped, json);
+
+        let mut unbrotlied = String::new();
+        let br = fs::read(dir.path().join("pages/page_1.json.br")).unwrap();
+        brotli::Decompressor::new(&br[..], 4096)
+            .read_to_string(&mut unbrotlied)
+            .unwrap();
+        assert_eq!(unbrotlied, json);
+    }
+}
@@ -0,0 +1,367 @@
+//! Moving build output into directories that may hold other files too, such
+//! as the trunk output the prerendered pages are written into.
+//!
+//! Every output directory has a list of the files Sinter put there, kept in
+//! a lists directory (`.sinter/deploy`) outside the served tree. A deploy
+//! copies the new build in, then removes the files listed by the previous one
+//! that the new build no longer has, so deleted posts and surplus pages stop
+//! being served. `sinter clean` removes exactly the listed files.
+//!
+//! Every file a deploy writes is listed, including files it overwrote in a
+//! `shared` directory (the app shell `index.html` that the entry page
+//! replaces); putting back what was there is up to the caller of [`clean`].
+//! Only files named in a list are ever removed. A directory that is not shared is expected to be
+//! Sinter's alone, so the first deploy into one that already holds files
+//! without a list is refused unless `allow_existing` is set; those files are
+//! then left in place.
+//!
+//! Each file is replaced by renaming a fully written sibling over it, and
+//! deeper files go first, so `site_data.json` and `index.html` never point at
+//! shards that are not in place yet. The deploy as a whole is not atomic: a
+//! failure part way through leaves some files old and some new, until the
+//! next successful build.
+
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Name of the list that older versions kept inside each output directory;
+/// it is read once in place of a missing list, then removed.
+const LEGACY_FILE_LIST: &str = ".sinter-files";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DeployReport {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// Copies every file below `src` into `output_dir` and prunes what the
+/// previous deploy left behind. `lists_dir` holds the generated file lists.
+pub fn deploy(
+    src: &Path,
+    output_dir: &Path,
+    lists_dir: &Path,
+    shared: bool,
+    allow_existing: bool,
+) -> Result<DeployReport> {
+    let previous = match read_list(output_dir, lists_dir)? {
+        Some(previous) => previous,
+        None => {
+            if !shared && !allow_existing && has_files(output_dir)? {
+                bail!(
+                    "{:?} already holds files that no earlier build recorded; pass \
+                     --allow-existing-output to deploy into it anyway (they are left in place)",
+                    output_dir
+                );
+            }
+            BTreeSet::new()
+        }
+    };
+    fs::create_dir_all(output_dir).context("Failed to create final output directory")?;
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(src) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            files.push(entry.path().strip_prefix(src)?.to_path_buf());
+        }
+    }
+    files.sort_by(|a, b| {
+        let depth = |path: &PathBuf| path.components().count();
+        depth(b).cmp(&depth(a)).then_with(|| a.cmp(b))
+    });
+
+    let mut report = DeployReport::default();
+    let mut generated = BTreeSet::new();
+    for rel_path in &files {
+        let source = src.join(rel_path);
+        let target = output_dir.join(rel_path);
+        generated.insert(list_entry(rel_path));
+        // Files whose bytes are already identical at the destination are left
+        // alone so their mtimes (and HTTP caches) stay valid.
+        if is_same_content(&source, &target)? {
+            report.unchanged += 1;
+        } else {
+            replace_file(&source, &target)?;
+            report.written += 1;
+        }
+    }
+
+    report.removed = write_list(output_dir, lists_dir, previous, generated)?;
+    Ok(report)
+}
+
+/// Records `files` (relative to `output_dir`) as the generated files of
+/// `output_dir`, removing the previously recorded ones that are not among
+/// them. Returns the number of files removed.
+pub fn record_files(output_dir: &Path, lists_dir: &Path, files: BTreeSet<String>) -> Result<usize> {
+    let previous = read_list(output_dir, lists_dir)?.unwrap_or_default();
+    write_list(output_dir, lists_dir, previous, files)
+}
+
+/// Removes every file recorded for `output_dir`, the list itself, and the
+/// directories left empty. Returns the number of files removed.
+pub fn clean(output_dir: &Path, lists_dir: &Path) -> Result<usize> {
+    let Some(files) = read_list(output_dir, lists_dir)? else {
+        return Ok(0);
+    };
+    let files: Vec<String> = files.into_iter().collect();
+    let removed = remove_files(output_dir, &files)?;
+    let list_path = list_path(output_dir, lists_dir)?;
+    if list_path.exists() {
+        fs::remove_file(&list_path).context("Failed to remove generated file list")?;
+    }
+    remove_legacy_list(output_dir)?;
+    // Only succeed if nothing else lives there.
+    let _ = fs::remove_dir(output_dir);
+    let _ = fs::remove_dir(lists_dir);
+    Ok(removed)
+}
+
+/// Writes `files` as the list of `output_dir` and removes the files of
+/// `previous` that are not among them. Returns the number of files removed.
+fn write_list(
+    output_dir: &Path,
+    lists_dir: &Path,
+    previous: BTreeSet<String>,
+    files: BTreeSet<String>,
+) -> Result<usize> {
+    let stale: Vec<String> = previous
+        .into_iter()
+        .filter(|path| !files.contains(path))
+        .collect();
+    let removed = remove_files(output_dir, &stale)?;
+
+    let mut list = String::new();
+    for path in &files {
+        list.push_str(path);
+        list.push('\n');
+    }
+    fs::create_dir_all(lists_dir).context("Failed to create deploy lists directory")?;
+    fs::write(list_path(output_dir, lists_dir)?, list)
+        .context("Failed to write generated file list")?;
+    remove_legacy_list(output_dir)?;
+    Ok(removed)
+}
+
+fn read_list(output_dir: &Path, lists_dir: &Path) -> Result<Option<BTreeSet<String>>> {
+    let mut path = list_path(output_dir, lists_dir)?;
+    if !path.exists() {
+        path = output_dir.join(LEGACY_FILE_LIST);
+        if !path.exists() {
+            return Ok(None);
+        }
+    }
+    let list = fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    Ok(Some(
+        list.lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+    ))
+}
+
+/// Where the list of `output_dir` is kept: named after the directory, plus a
+/// hash of its absolute path so equally named directories do not share one.
+fn list_path(output_dir: &Path, lists_dir: &Path) -> Result<PathBuf> {
+    let absolute = std::path::absolute(output_dir)
+        .with_context(|| format!("Failed to resolve {:?}", output_dir))?;
+    let name: String = absolute
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let hash = blake3::hash(absolute.to_string_lossy().as_bytes()).to_hex();
+    Ok(lists_dir.join(format!("{}-{}.txt", name, &hash[..12])))
+}
+
+fn remove_legacy_list(output_dir: &Path) -> Result<()> {
+    let path = output_dir.join(LEGACY_FILE_LIST);
+    if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+    }
+    Ok(())
+}
+
+/// Whether there is any file below `dir`.
+fn has_files(dir: &Path) -> Result<bool> {
+    if !dir.exists() {
+        return Ok(false);
+    }
+    for entry in WalkDir::new(dir) {
+        if entry?.file_type().is_file() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Removes `files` below `output_dir` and then their parent directories, as
+/// far up as they are empty.
+fn remove_files(output_dir: &Path, files: &[String]) -> Result<usize> {
+    let mut removed = 0;
+    for file in files {
+        let rel_path = Path::new(file);
+        // The list is plain text on disk; never follow it outside the directory.
+        if !rel_path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            bail!("Refusing to remove {:?} listed for {:?}", file, output_dir);
+        }
+
+        let path = output_dir.join(rel_path);
+        if path.is_file() {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+            removed += 1;
+        }
+        for dir in rel_path.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() || fs::remove_dir(output_dir.join(dir)).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// Writes `source` to `target` through a temporary sibling, so the target is
+/// always either the old or the new file.
+fn replace_file(source: &Path, target: &Path) -> Result<()> {
+    let parent = target
+        .parent()
+        .context("Output file has no parent directory")?;
+    fs::create_dir_all(parent)?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(target.file_name().context("Output file has no name")?);
+    temp_name.push(".tmp");
+    let temp = parent.join(temp_name);
+
+    fs::copy(source, &temp).with_context(|| format!("Failed to write {:?}", temp))?;
+    fs::rename(&temp, target).with_context(|| format!("Failed to replace {:?}", target))?;
+    Ok(())
+}
+
+fn list_entry(rel_path: &Path) -> String {
+    rel_path.to_string_lossy().replace('\\', "/")
+}
+
+fn is_same_content(src: &Path, dst: &Path) -> Result<bool> {
+    let Ok(dst_meta) = fs::metadata(dst) else {
+        return Ok(false);
+    };
+    if !dst_meta.is_file() || dst_meta.len() != fs::metadata(src)?.len() {
+        return Ok(false);
+    }
+    Ok(fs::read(src)? == fs::read(dst)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_deploy_prunes_and_cleans() {
+        let root = tempfile::tempdir().unwrap();
+        let out = root.path().join("dist");
+        let lists = root.path().join(".sinter/deploy");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join("index.html"), "not ours").unwrap();
+
+        let first = build(&[
+            ("site_data.json", "1"),
+            ("posts/a.json", "a"),
+            ("posts/old.json", "old"),
+            ("tags/rust/page_1.json", "t"),
+        ]);
+        let report = deploy(first.path(), &out, &lists, true, false).unwrap();
+        assert_eq!((report.written, report.removed), (4, 0));
+
+        let second = build(&[
+            ("site_data.json", "2"),
+            ("posts/a.json", "a"),
+            ("index.html", "prerendered"),
+        ]);
+        let report = deploy(second.path(), &out, &lists, true, false).unwrap();
+        assert_eq!(
+            report,
+            DeployReport {
+                written: 2,
+                unchanged: 1,
+                removed: 2
+            }
+        );
+        assert!(!out.join("posts/old.json").exists());
+        assert!(!out.join("tags").exists());
+        assert_eq!(fs::read_to_string(out.join("site_data.json")).unwrap(), "2");
+        // The list is kept out of the served tree.
+        assert_eq!(fs::read_dir(&lists).unwrap().count(), 1);
+        assert!(!out.join(LEGACY_FILE_LIST).exists());
+
+        // A file that predates Sinter is claimed once it is overwritten, so
+        // no stale copy outlives `clean`.
+        assert_eq!(clean(&out, &lists).unwrap(), 3);
+        assert!(!out.join("posts").exists());
+        assert!(!out.join("index.html").exists());
+        assert!(!lists.exists());
+    }
+
+    #[test]
+    fn test_first_deploy_into_unowned_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let out = root.path().join("sinter_data");
+        let lists = root.path().join(".sinter/deploy");
+        fs::create_dir_all(out.join("notes")).unwrap();
+        fs::write(out.join("notes/todo.txt"), "mine").unwrap();
+        fs::write(out.join("site_data.json"), "old").unwrap();
+
+        // Files nobody recorded are never taken for stale output.
+        let first = build(&[("site_data.json", "1"), ("posts/a.json", "a")]);
+        let err = deploy(first.path(), &out, &lists, false, false).unwrap_err();
+        assert!(err.to_string().contains("--allow-existing-output"));
+        assert_eq!(
+            fs::read_to_string(out.join("site_data.json")).unwrap(),
+            "old"
+        );
+
+        let report = deploy(first.path(), &out, &lists, false, true).unwrap();
+        assert_eq!((report.written, report.removed), (2, 0));
+        assert!(out.join("notes/todo.txt").exists());
+
+        // From then on only what Sinter wrote is pruned.
+        let second = build(&[("site_data.json", "2")]);
+        let report = deploy(second.path(), &out, &lists, false, false).unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(!out.join("posts").exists());
+        assert!(out.join("notes/todo.txt").exists());
+
+        // An empty or missing directory needs no permission.
+        let fresh = root.path().join("fresh");
+        deploy(second.path(), &fresh, &lists, false, false).unwrap();
+
+        // A list left inside the directory by an older version is honoured, then moved out.
+        let other = root.path().join("themes");
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("old.css"), "").unwrap();
+        fs::write(other.join("kept.css"), "").unwrap();
+        fs::write(other.join(LEGACY_FILE_LIST), "old.css\n").unwrap();
+        let removed = record_files(&other, &lists, BTreeSet::new()).unwrap();
+        assert_eq!(removed, 1);
+        assert!(other.join("kept.css").exists());
+        assert!(!other.join(LEGACY_FILE_LIST).exists());
+    }
+}
@@ -1,8 +1,13 @@
+mod check;
 mod compiler;
+mod compress;
+mod deploy;
+mod serve;
 mod themes;
 
 use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
+use sinter_core::encoding::DataFormat;
 use std::path::PathBuf;
 use tracing::{Level, info};
 use tracing_subscriber::FmtSubscriber;
@@ -18,9 +23,15 @@ struct Cli {
 enum Commands {
     /// Build the site
     Build(BuildArgs),
+    /// Serve the site locally and rebuild on changes
+    Serve(ServeArgs),
+    /// Validate content without writing any output
+    Check(CheckArgs),
+    /// Remove everything previous builds generated
+    Clean(CleanArgs),
 }
 
-#[derive(Args, Debug)]
+#[derive(Args, Debug, Clone)]
 struct BuildArgs {
     /// Path to posts directory
     #[arg(short, long = "posts", default_value = "./posts")]
@@ -49,49 +60,250 @@ struct BuildArgs {
     /// Themes output directory
     #[arg(long, default_value = "./sinter_web/themes")]
     themes_output: PathBuf,
+
+    /// Directory that root-relative image URLs are resolved against
+    #[arg(long, default_value = "./sinter_web")]
+    static_dir: PathBuf,
+
+    /// Build cache directory
+    #[arg(long, default_value = "./.sinter/cache")]
+    cache_dir: PathBuf,
+
+    /// Directory the lists of files each build wrote are kept in
+    #[arg(long, default_value = "./.sinter/deploy")]
+    deploy_dir: PathBuf,
+
+    /// Deploy into a data output directory that already holds files no
+    /// earlier build recorded; they are left in place
+    #[arg(long)]
+    allow_existing_output: bool,
+
+    /// Parse every file from scratch instead of using the build cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Fail the build if any post cannot be parsed
+    #[arg(long)]
+    strict: bool,
+
+    /// Include posts marked `draft: true`
+    #[arg(long)]
+    drafts: bool,
+
+    /// Include posts dated after today
+    #[arg(long)]
+    future: bool,
+
+    /// Write JSON shards under content-hashed names listed in a manifest
+    #[arg(long)]
+    hashed_filenames: bool,
+
+    /// Encoding of post and page shards: json or msgpack
+    #[arg(long, default_value = "json")]
+    format: DataFormat,
+
+    /// Write gzip and brotli copies (`.gz`, `.br`) next to every JSON shard and theme CSS file
+    #[arg(long)]
+    precompress: bool,
+
+    /// Write static HTML for every route into the built web app
+    #[arg(long)]
+    prerender: bool,
+
+    /// Built web app (trunk output) to write files served from the site root
+    /// into: the entry page, sitemap.xml and robots.txt. Defaults to
+    /// ./sinter_web/dist with --prerender; without either, it is left alone
+    #[arg(long)]
+    web_root: Option<PathBuf>,
+}
+
+/// Where `trunk build` puts the web app.
+const DEFAULT_WEB_ROOT: &str = "./sinter_web/dist";
+
+impl BuildArgs {
+    /// The built web app, whether or not builds write into it.
+    fn web_root(&self) -> PathBuf {
+        self.web_root
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_WEB_ROOT))
+    }
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// Address to bind the dev server to
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to bind the dev server to
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+}
+
+#[derive(Args, Debug)]
+struct CheckArgs {
+    /// Path to posts directory
+    #[arg(short, long = "posts", default_value = "./posts")]
+    posts_dir: PathBuf,
+
+    /// Path to archives directory
+    #[arg(short, long = "archives", default_value = "./archives")]
+    archives_dir: PathBuf,
+
+    /// Path to config file
+    #[arg(short, long, default_value = "./sinter.toml")]
+    config: PathBuf,
+
+    /// Directory that root-relative image URLs are resolved against
+    #[arg(long, default_value = "./sinter_web")]
+    static_dir: PathBuf,
+
+    /// Treat posts marked `draft: true` as published, like `build --drafts`
+    #[arg(long)]
+    drafts: bool,
+
+    /// Treat posts dated after today as published, like `build --future`
+    #[arg(long)]
+    future: bool,
+
+    /// Enable verbose logging
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[derive(Args, Debug)]
+struct CleanArgs {
+    /// Data output directory
+    #[arg(short, long, default_value = "./sinter_web/sinter_data")]
+    data_output: PathBuf,
+
+    /// Themes output directory
+    #[arg(long, default_value = "./sinter_web/themes")]
+    themes_output: PathBuf,
+
+    /// Built web app that root files and prerendered pages were written into
+    #[arg(long, default_value = "./sinter_web/dist")]
+    web_root: PathBuf,
+
+    /// Build cache directory
+    #[arg(long, default_value = "./.sinter/cache")]
+    cache_dir: PathBuf,
+
+    /// Directory the lists of files each build wrote are kept in
+    #[arg(long, default_value = "./.sinter/deploy")]
+    deploy_dir: PathBuf,
+
+    /// Enable verbose logging
+    #[arg(short, long)]
+    verbose: bool,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    let verbose = match &cli.command {
+        Commands::Build(args) => args.verbose,
+        Commands::Serve(args) => args.build.verbose,
+        Commands::Check(args) => args.verbose,
+        Commands::Clean(args) => args.verbose,
+    };
+    init_logging(verbose);
+
     match &cli.command {
         Commands::Build(args) => {
-            // Initialize logging
-            let log_level = if args.verbose {
-                Level::DEBUG
-            } else {
-                Level::INFO
-            };
+            info!("Starting Sinter compilation...");
+            build_themes(args)?;
+            build_content(args)?;
+        }
+        Commands::Serve(args) => {
+            serve::serve(args)?;
+        }
+        Commands::Check(args) => {
+            check::check(&check::CheckOptions {
+                posts_dir: &args.posts_dir,
+                archives_dir: &args.archives_dir,
+                config_path: &args.config,
+                static_dir: &args.static_dir,
+                drafts: args.drafts,
+                future: args.future,
+            })?;
+        }
+        Commands::Clean(args) => clean(args)?,
+    }
 
-            let subscriber = FmtSubscriber::builder().with_max_level(log_level).finish();
-            tracing::subscriber::set_global_default(subscriber)
-                .expect("setting default subscriber failed");
+    Ok(())
+}
 
-            info!("Starting Sinter compilation...");
+fn init_logging(verbose: bool) {
+    let log_level = if verbose { Level::DEBUG } else { Level::INFO };
 
-            // Process themes
-            if args.themes_config.exists() {
-                themes::process_themes(&args.themes_config, &args.themes_output)?;
-            } else {
-                info!(
-                    "Themes configuration not found at {:?}, skipping theme build.",
-                    args.themes_config
-                );
-            }
-
-            info!("Posts directory: {:?}", args.posts_dir);
-            info!("Archives directory: {:?}", args.archives_dir);
-            info!("Data output directory: {:?}", args.data_output);
-
-            // Implement core compilation logic here
-            compiler::compile(
-                &args.posts_dir,
-                &args.archives_dir,
-                &args.data_output,
-                &args.config,
-            )?;
-        }
+    let subscriber = FmtSubscriber::builder().with_max_level(log_level).finish();
+    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+}
+
+/// Removes the files recorded by the last build in each output directory and
+/// the build cache. Anything Sinter did not write is left alone.
+fn clean(args: &CleanArgs) -> Result<()> {
+    for dir in [&args.data_output, &args.themes_output] {
+        let removed = deploy::clean(dir, &args.deploy_dir)?;
+        info!("Removed {} generated file(s) from {:?}", removed, dir);
     }
+    let removed = compiler::clean_web_root(&args.web_root, &args.deploy_dir)?;
+    info!(
+        "Removed {} generated file(s) from {:?}",
+        removed, args.web_root
+    );
+    let removed = compiler::clear_cache(&args.cache_dir)?;
+    info!(
+        "Removed {} cache entries from {:?}",
+        removed, args.cache_dir
+    );
+    Ok(())
+}
 
+fn build_themes(args: &BuildArgs) -> Result<()> {
+    if args.themes_config.exists() {
+        themes::process_themes(
+            &args.themes_config,
+            &args.themes_output,
+            &args.deploy_dir,
+            args.precompress,
+        )?;
+    } else {
+        info!(
+            "Themes configuration not found at {:?}, skipping theme build.",
+            args.themes_config
+        );
+    }
     Ok(())
 }
+
+fn build_content(args: &BuildArgs) -> Result<()> {
+    info!("Posts directory: {:?}", args.posts_dir);
+    info!("Archives directory: {:?}", args.archives_dir);
+    info!("Data output directory: {:?}", args.data_output);
+
+    // trunk wipes its output on every build, so it is only written into when asked.
+    let web_root = (args.prerender || args.web_root.is_some()).then(|| args.web_root());
+    compiler::compile(&compiler::CompileOptions {
+        posts_dir: &args.posts_dir,
+        archives_dir: &args.archives_dir,
+        data_output_dir: &args.data_output,
+        config_path: &args.config,
+        static_dir: Some(&args.static_dir),
+        cache_dir: (!args.no_cache).then_some(args.cache_dir.as_path()),
+        deploy_dir: &args.deploy_dir,
+        allow_existing_output: args.allow_existing_output,
+        strict: args.strict,
+        drafts: args.drafts,
+        future: args.future,
+        hashed_filenames: args.hashed_filenames,
+        precompress: args.precompress,
+        format: args.format,
+        prerender: args.prerender,
+        web_root: web_root.as_deref(),
+    })
+}
@@ -0,0 +1,468 @@
+use crate::{BuildArgs, ServeArgs, build_content, build_themes};
+use anyhow::{Context, Result, anyhow};
+use image::ImageFormat;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use percent_encoding::percent_decode_str;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Request, Response, Server};
+use tracing::{debug, error, info, warn};
+
+/// Endpoint the injected client script subscribes to (Server-Sent Events).
+const RELOAD_ENDPOINT: &str = "/__sinter/reload";
+
+const RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+    var source = new EventSource("/__sinter/reload");
+    source.onmessage = function () { window.location.reload(); };
+})();
+</script>"#;
+
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Which parts of the build need to run again after a batch of file changes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct RebuildPlan {
+    content: bool,
+    themes: bool,
+}
+
+impl RebuildPlan {
+    fn is_empty(&self) -> bool {
+        !self.content && !self.themes
+    }
+}
+
+/// Paths watched by the dev server, resolved once so event paths can be compared.
+struct WatchTargets {
+    posts_dir: PathBuf,
+    archives_dir: PathBuf,
+    config: PathBuf,
+    themes_config: PathBuf,
+    static_dir: PathBuf,
+    /// Directories the build writes into; several sit inside the static dir.
+    outputs: Vec<PathBuf>,
+}
+
+impl WatchTargets {
+    fn new(args: &BuildArgs) -> Self {
+        Self {
+            posts_dir: absolute(&args.posts_dir),
+            archives_dir: absolute(&args.archives_dir),
+            config: absolute(&args.config),
+            themes_config: absolute(&args.themes_config),
+            static_dir: absolute(&args.static_dir),
+            outputs: [
+                &args.data_output,
+                &args.themes_output,
+                &args.web_root(),
+                &args.cache_dir,
+                &args.deploy_dir,
+            ]
+            .into_iter()
+            .map(|dir| absolute(dir))
+            .collect(),
+        }
+    }
+
+    fn classify(&self, changed: &[PathBuf]) -> RebuildPlan {
+        let mut plan = RebuildPlan::default();
+        for path in changed {
+            let path = absolute(path);
+            if self.outputs.iter().any(|dir| path.starts_with(dir)) {
+                continue;
+            }
+            if path == self.themes_config {
+                plan.themes = true;
+            } else if path == self.config
+                || path.starts_with(&self.posts_dir)
+                || path.starts_with(&self.archives_dir)
+            {
+                plan.content = true;
+            } else if path.starts_with(&self.static_dir) && ImageFormat::from_path(&path).is_ok() {
+                // Posts record the size and preview of root-relative images.
+                plan.content = true;
+            }
+        }
+        plan
+    }
+}
+
+/// Connected live-reload clients. Each subscriber is woken once per rebuild.
+#[derive(Clone, Default)]
+struct ReloadHub {
+    subscribers: Arc<Mutex<Vec<Sender<()>>>>,
+}
+
+impl ReloadHub {
+    fn subscribe(&self) -> Receiver<()> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    fn notify(&self) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        // Disconnected clients have dropped their receiver; prune them here.
+        subscribers.retain(|tx| tx.send(()).is_ok());
+        info!("Live reload sent to {} client(s).", subscribers.len());
+    }
+}
+
+pub fn serve(args: &ServeArgs) -> Result<()> {
+    info!("Starting Sinter dev server...");
+
+    // Initial full build so the server never starts with stale data.
+    build_themes(&args.build)?;
+    if let Err(e) = build_content(&args.build) {
+        error!("Initial build failed: {:?}", e);
+    }
+
+    let web_root = args.build.web_root();
+    if !web_root.exists() {
+        warn!(
+            "Web root {:?} does not exist. Run `trunk build` in sinter_web first.",
+            web_root
+        );
+    }
+
+    let hub = ReloadHub::default();
+
+    let watch_args = args.build.clone();
+    let watch_hub = hub.clone();
+    thread::spawn(move || {
+        if let Err(e) = watch_and_rebuild(&watch_args, &watch_hub) {
+            error!("File watcher stopped: {:?}", e);
+        }
+    });
+
+    let addr = format!("{}:{}", args.host, args.port);
+    let server = Server::http(&addr).map_err(|e| anyhow!("Failed to bind {}: {}", addr, e))?;
+    info!("Serving on http://{}", addr);
+
+    let roots = Arc::new(ServeRoots {
+        web_root,
+        data_dir: args.build.data_output.clone(),
+        themes_dir: args.build.themes_output.clone(),
+    });
+
+    for request in server.incoming_requests() {
+        let roots = roots.clone();
+        let hub = hub.clone();
+        // Event streams block for the lifetime of the tab, so every request gets its own thread.
+        thread::spawn(move || {
+            if let Err(e) = handle_request(request, &roots, &hub) {
+                debug!("Request failed: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn watch_and_rebuild(args: &BuildArgs, hub: &ReloadHub) -> Result<()> {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to create watcher")?;
+
+    for dir in [&args.posts_dir, &args.archives_dir, &args.static_dir] {
+        if dir.exists() {
+            watcher
+                .watch(dir, RecursiveMode::Recursive)
+                .with_context(|| format!("Failed to watch {:?}", dir))?;
+            info!("Watching {:?}", dir);
+        }
+    }
+
+    // Editors often replace files by renaming, which breaks watches on the file itself,
+    // so single files are watched through their parent directory.
+    for file in [&args.config, &args.themes_config] {
+        let parent = absolute(file)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        if parent.exists() {
+            watcher
+                .watch(&parent, RecursiveMode::NonRecursive)
+                .with_context(|| format!("Failed to watch {:?}", parent))?;
+            info!("Watching {:?}", file);
+        }
+    }
+
+    let targets = WatchTargets::new(args);
+
+    while let Ok(first) = rx.recv() {
+        // Collect the burst of events an editor save produces before rebuilding.
+        let mut changed = Vec::new();
+        collect_changes(first, &mut changed);
+        while let Ok(next) = rx.recv_timeout(DEBOUNCE_TIMEOUT) {
+            collect_changes(next, &mut changed);
+        }
+
+        let plan = targets.classify(&changed);
+        if plan.is_empty() {
+            continue;
+        }
+
+        info!("Change detected, rebuilding {:?}", plan);
+        debug!("Changed paths: {:?}", changed);
+
+        // A broken theme must not hold back the content, and vice versa.
+        let mut rebuilt = false;
+        if plan.themes {
+            match build_themes(args) {
+                Ok(()) => rebuilt = true,
+                Err(e) => error!("Theme rebuild failed: {:?}", e),
+            }
+        }
+        if plan.content {
+            match build_content(args) {
+                Ok(()) => rebuilt = true,
+                Err(e) => error!("Content rebuild failed: {:?}", e),
+            }
+        }
+
+        if rebuilt {
+            hub.notify();
+        }
+    }
+
+    Ok(())
+}
+
+/// Keeps only events that change file contents; the rebuild itself reads
+/// every source file, so access events would retrigger it forever.
+fn collect_changes(result: notify::Result<Event>, changed: &mut Vec<PathBuf>) {
+    match result {
+        Ok(event) => {
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                changed.extend(event.paths);
+            }
+        }
+        Err(e) => warn!("Watch error: {:?}", e),
+    }
+}
+
+struct ServeRoots {
+    web_root: PathBuf,
+    data_dir: PathBuf,
+    themes_dir: PathBuf,
+}
+
+impl ServeRoots {
+    /// Maps a request path onto a file, preferring the live build output for
+    /// `/sinter_data` and `/themes` over the copies trunk placed in the web root.
+    fn resolve(&self, url_path: &str) -> Option<PathBuf> {
+        let (root, rest) = if let Some(rest) = url_path.strip_prefix("/sinter_data/") {
+            (&self.data_dir, rest)
+        } else if let Some(rest) = url_path.strip_prefix("/themes/") {
+            (&self.themes_dir, rest)
+        } else {
+            (&self.web_root, url_path.trim_start_matches('/'))
+        };
+
+        let relative = Path::new(rest);
+        // Reject anything that could escape the served directory.
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return None;
+        }
+
+        let mut path = root.join(relative);
+        if path.is_dir() {
+            path = path.join("index.html");
+        }
+        path.is_file().then_some(path)
+    }
+}
+
+fn handle_request(request: Request, roots: &ServeRoots, hub: &ReloadHub) -> Result<()> {
+    let url = request.url().to_string();
+    let raw_path = url.split(['?', '#']).next().unwrap_or("/");
+    let path = percent_decode_str(raw_path)
+        .decode_utf8_lossy()
+        .into_owned();
+
+    if path == RELOAD_ENDPOINT {
+        return stream_reload_events(request, hub);
+    }
+
+    match roots.resolve(&path) {
+        Some(file) => respond_with_file(request, &file),
+        None if is_asset_path(&path) => {
+            request.respond(Response::from_string("404 Not Found").with_status_code(404))?;
+            Ok(())
+        }
+        // Client-side routes (e.g. `/posts/<slug>`) fall back to the app shell.
+        None => match roots.resolve("/index.html") {
+            Some(index) => respond_with_file(request, &index),
+            None => {
+                request.respond(
+                    Response::from_string("index.html not found in web root").with_status_code(404),
+                )?;
+                Ok(())
+            }
+        },
+    }
+}
+
+fn respond_with_file(request: Request, file: &Path) -> Result<()> {
+    let mime = mime_type(file);
+    let mut body = fs::read(file).with_context(|| format!("Failed to read {:?}", file))?;
+
+    if mime.starts_with("text/html") {
+        body = inject_reload_script(&String::from_utf8_lossy(&body)).into_bytes();
+    }
+
+    let response = Response::from_data(body)
+        .with_header(header("Content-Type", mime))
+        .with_header(header("Cache-Control", "no-store"));
+    request.respond(response)?;
+    Ok(())
+}
+
+fn stream_reload_events(request: Request, hub: &ReloadHub) -> Result<()> {
+    let events = hub.subscribe();
+    let mut writer = request.into_writer();
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n"
+    )?;
+    writer.flush()?;
+
+    // Returns once the hub is dropped or the browser goes away (write error).
+    while events.recv().is_ok() {
+        write!(writer, "data: reload\n\n")?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn inject_reload_script(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(idx) => format!("{}{}{}", &html[..idx], RELOAD_SCRIPT, &html[idx..]),
+        None => format!("{}{}", html, RELOAD_SCRIPT),
+    }
+}
+
+fn is_asset_path(path: &str) -> bool {
+    path.starts_with("/sinter_data/")
+        || path.starts_with("/themes/")
+        || Path::new(path).extension().is_some()
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "html" => "text/html; charset=utf-8",
+        "js" => "application/javascript",
+        "wasm" => "application/wasm",
+        "css" => "text/css",
+        "json" => "application/json",
+        "msgpack" => "application/msgpack",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff2" => "font/woff2",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("static header is valid")
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets() -> WatchTargets {
+        WatchTargets {
+            posts_dir: PathBuf::from("/site/posts"),
+            archives_dir: PathBuf::from("/site/archives"),
+            config: PathBuf::from("/site/sinter.toml"),
+            themes_config: PathBuf::from("/site/sinter_themes/themes.toml"),
+            static_dir: PathBuf::from("/site/sinter_web"),
+            outputs: vec![
+                PathBuf::from("/site/sinter_web/sinter_data"),
+                PathBuf::from("/site/sinter_web/dist"),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_classify_changes() {
+        let t = targets();
+
+        let plan = t.classify(&[PathBuf::from("/site/posts/hello.md")]);
+        assert_eq!(
+            plan,
+            RebuildPlan {
+                content: true,
+                themes: false
+            }
+        );
+
+        let plan = t.classify(&[PathBuf::from("/site/sinter_themes/themes.toml")]);
+        assert_eq!(
+            plan,
+            RebuildPlan {
+                content: false,
+                themes: true
+            }
+        );
+
+        let plan = t.classify(&[PathBuf::from("/site/sinter_web/images/a.png")]);
+        assert_eq!(
+            plan,
+            RebuildPlan {
+                content: true,
+                themes: false
+            }
+        );
+
+        // Build output inside the static dir must not retrigger the build.
+        for path in [
+            "/site/README.md",
+            "/site/sinter_web/src/main.rs",
+            "/site/sinter_web/sinter_data/assets/a.0123456789.png",
+            "/site/sinter_web/dist/index.html",
+        ] {
+            assert!(t.classify(&[PathBuf::from(path)]).is_empty(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_inject_reload_script() {
+        let html = "<html><body><p>hi</p></body></html>";
+        let out = inject_reload_script(html);
+        assert!(out.contains(RELOAD_ENDPOINT));
+        assert!(out.ends_with("</body></html>"));
+    }
+
+    #[test]
+    fn test_resolve_rejects_traversal() {
+        let roots = ServeRoots {
+            web_root: PathBuf::from("."),
+            data_dir: PathBuf::from("."),
+            themes_dir: PathBuf::from("."),
+        };
+        assert!(roots.resolve("/sinter_data/../Cargo.toml").is_none());
+    }
+}
@@ -1,7 +1,8 @@
+use crate::{compress, deploy};
 use anyhow::{Context, Result, bail};
 use rayon::prelude::*;
 use serde::Deserialize;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -23,7 +24,12 @@ pub struct ThemesConfig {
     pub theme: Vec<ThemeConfig>,
 }
 
-pub fn process_themes(themes_config_path: &Path, web_style_dir: &Path) -> Result<()> {
+pub fn process_themes(
+    themes_config_path: &Path,
+    web_style_dir: &Path,
+    deploy_dir: &Path,
+    precompress: bool,
+) -> Result<()> {
     info!(
         "Processing themes configuration from {:?}",
         themes_config_path
@@ -158,5 +164,33 @@ pub fn process_themes(themes_config_path: &Path, web_style_dir: &Path) -> Result
         }
     });
 
+    if precompress {
+        let savings = compress::compress_dir(web_style_dir, &["css"])?;
+        info!("Precompressed theme CSS: {}", savings);
+    }
+
+    // Claim the files of every configured theme that are in place, so a theme
+    // whose build failed keeps its previous CSS while removed themes are pruned.
+    let suffixes: &[&str] = if precompress {
+        &["", ".gz", ".br"]
+    } else {
+        &[""]
+    };
+    let mut generated = BTreeSet::new();
+    for theme in &config.theme {
+        for file_name in &theme.files {
+            for suffix in suffixes {
+                let rel_path = format!("{}/{}{}", theme.name, file_name, suffix);
+                if web_style_dir.join(&rel_path).is_file() {
+                    generated.insert(rel_path);
+                }
+            }
+        }
+    }
+    let removed = deploy::record_files(web_style_dir, deploy_dir, generated)?;
+    if removed > 0 {
+        info!("Removed {} stale theme file(s).", removed);
+    }
+
     Ok(())
 }
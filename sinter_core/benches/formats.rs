@@ -0,0 +1,219 @@
+//! Transfer size and decode time of the data shards, JSON vs MessagePack.
+//!
+//!     cargo bench -p sinter_core --features msgpack
+//!
+//! Measures a synthetic long post and page by default. Point
+//! `SINTER_BENCH_DATA` at the output of `sinter build` (JSON format) to
+//! measure every post and page of a real site instead.
+//!
+//! Decoding runs natively with `serde_json`; in the browser JSON additionally
+//! goes through `JSON.parse` and `serde_wasm_bindgen`, so the gap there is
+//! wider than reported here.
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sinter_core::encoding::{from_msgpack, to_msgpack};
+use sinter_core::{
+    CodeToken, ContentNode, LiteDate, PageData, Post, PostMetadata, SitePostMetadata, TokenKind,
+};
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200;
+
+fn main() {
+    let (posts, pages) = match std::env::var_os("SINTER_BENCH_DATA") {
+        Some(dir) => load_site(Path::new(&dir)),
+        None => {
+            let post = synthetic_post();
+            let page = synthetic_page(&post.metadata);
+            (vec![post], vec![page])
+        }
+    };
+
+    println!(
+        "{:<6} {:>8} {:>10} {:>10} {:>12}",
+        "", "format", "bytes", "gzip", "decode"
+    );
+    report("Post", &posts);
+    report("Page", &pages);
+}
+
+fn report<T: Serialize + DeserializeOwned>(label: &str, values: &[T]) {
+    let json: Vec<Vec<u8>> = values
+        .iter()
+        .map(|v| serde_json::to_vec(v).unwrap())
+        .collect();
+    let msgpack: Vec<Vec<u8>> = values.iter().map(|v| to_msgpack(v).unwrap()).collect();
+
+    let json_time = time(|| {
+        for bytes in &json {
+            black_box(serde_json::from_slice::<T>(bytes).unwrap());
+        }
+    });
+    let msgpack_time = time(|| {
+        for bytes in &msgpack {
+            black_box(from_msgpack::<T>(bytes).unwrap());
+        }
+    });
+
+    for (format, encoded, elapsed) in [
+        ("json", &json, json_time),
+        ("msgpack", &msgpack, msgpack_time),
+    ] {
+        let raw: usize = encoded.iter().map(Vec::len).sum();
+        let gzip: usize = encoded.iter().map(|bytes| gzip_len(bytes)).sum();
+        println!(
+            "{:<6} {:>8} {:>10} {:>10} {:>10.1}µs",
+            label,
+            format,
+            raw,
+            gzip,
+            elapsed.as_secs_f64() * 1e6
+        );
+    }
+}
+
+/// Average time of one call of `f`.
+fn time(mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn gzip_len(bytes: &[u8]) -> usize {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap().len()
+}
+
+fn load_site(dir: &Path) -> (Vec<Post>, Vec<PageData>) {
+    fn load<T: DeserializeOwned>(dir: &Path) -> Vec<T> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap())
+            .collect()
+    }
+    let mut posts = load(&dir.join("posts"));
+    posts.extend(load(&dir.join("archives")));
+    let mut pages = load(&dir.join("pages"));
+    pages.extend(load(&dir.join("archives/pages")));
+    (posts, pages)
+}
+
+fn text(value: &str) -> ContentNode {
+    ContentNode::Text {
+        value: value.to_string(),
+    }
+}
+
+fn synthetic_post() -> Post {
+    let mut content_ast = Vec::new();
+    for section in 0..20 {
+        content_ast.push(ContentNode::Heading {
+            level: 2,
+            id: Some(format!("section-{}", section)),
+            classes: vec![],
+            children: vec![text(&format!("Section {}", section))],
+        });
+        for _ in 0..4 {
+            content_ast.push(ContentNode::Paragraph {
+                children: vec![
+                    text("Static sites trade runtime flexibility for "),
+                    ContentNode::Strong {
+                        children: vec![text("speed")],
+                    },
+                    text(", and the compiler does the heavy lifting: "),
+                    ContentNode::InlineCode {
+                        value: "parse_post".to_string(),
+                    },
+                    text(" turns Markdown into an AST once, see "),
+                    ContentNode::Link {
+                        url: "/posts/rayon-deep-dive".to_string(),
+                        title: None,
+                        children: vec![text("the Rayon notes")],
+                    },
+                    text("."),
+                ],
+            });
+        }
+        let line = [
+            (Some(TokenKind::Keyword), "let"),
+            (None, " "),
+            (Some(TokenKind::Variable), "posts"),
+            (None, " = "),
+            (Some(TokenKind::Function), "load_posts"),
+            (Some(TokenKind::Punctuation), "("),
+            (Some(TokenKind::String), "\"posts\""),
+            (Some(TokenKind::Punctuation), ");\n"),
+        ];
+        let tokens: Vec<CodeToken> = (0..6)
+            .flat_map(|_| line)
+            .map(|(kind, text)| CodeToken {
+                kind,
+                text: text.to_string(),
+            })
+            .collect();
+        content_ast.push(ContentNode::CodeBlock {
+            lang: Some("rust".to_string()),
+            code_text: tokens.iter().map(|t| t.text.as_str()).collect(),
+            tokens,
+        });
+    }
+
+    Post {
+        metadata: PostMetadata {
+            id: "1".to_string(),
+            title: "A long post".to_string(),
+            slug: "a-long-post".to_string(),
+            date: LiteDate {
+                year: 2024,
+                month: 5,
+                day: 1,
+            },
+            tags: vec!["rust".to_string(), "wasm".to_string()],
+            tag_slugs: vec![],
+            draft: false,
+            summary: "Static sites trade runtime flexibility for speed.".to_string(),
+            word_count: 2400,
+            reading_time: 12,
+        },
+        content_ast,
+        toc: vec![],
+    }
+}
+
+fn synthetic_page(metadata: &PostMetadata) -> PageData {
+    let posts: Vec<SitePostMetadata> = (0..10)
+        .map(|i| SitePostMetadata {
+            metadata: PostMetadata {
+                slug: format!("post-{}", i),
+                ..metadata.clone()
+            },
+            path: format!("posts/post-{}.json", i),
+        })
+        .collect();
+    let mut tags_index = BTreeMap::new();
+    for post in &posts {
+        for tag in &post.metadata.tags {
+            tags_index
+                .entry(tag.clone())
+                .or_insert_with(Vec::new)
+                .push(post.metadata.slug.clone());
+        }
+    }
+    PageData { posts, tags_index }
+}
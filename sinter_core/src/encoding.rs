@@ -0,0 +1,152 @@
+//! Encodings the compiler can write `Post` and `PageData` shards in.
+//!
+//! JSON is always available. The `msgpack` feature adds MessagePack, which is
+//! smaller on the wire and decodes without going through JS objects in WASM.
+//! Structs are encoded as maps keyed by field name, so the optional and
+//! defaulted fields, flattened metadata and tagged `ContentNode`s behave
+//! exactly as they do in JSON.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Encoding of the post and page shards of a build.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataFormat {
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+impl DataFormat {
+    pub const ALL: [DataFormat; 2] = [DataFormat::Json, DataFormat::MessagePack];
+
+    /// File extension of shards in this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            DataFormat::Json => "json",
+            DataFormat::MessagePack => "msgpack",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            DataFormat::Json => "application/json",
+            DataFormat::MessagePack => "application/msgpack",
+        }
+    }
+
+    /// The format a `Content-Type` header value names, ignoring parameters
+    /// such as `charset`. `application/x-msgpack` is accepted as an alias.
+    pub fn from_mime_type(content_type: &str) -> Option<DataFormat> {
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "application/json" => Some(DataFormat::Json),
+            "application/msgpack" | "application/x-msgpack" => Some(DataFormat::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// The format of a file, judged by the extension of its path or URL.
+    pub fn from_path(path: &str) -> Option<DataFormat> {
+        let path = path.split(['?', '#']).next().unwrap_or(path);
+        let (_, extension) = path.rsplit_once('.')?;
+        DataFormat::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+
+    pub fn is_json(&self) -> bool {
+        *self == DataFormat::Json
+    }
+}
+
+impl fmt::Display for DataFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for DataFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DataFormat::ALL
+            .into_iter()
+            .find(|format| format.extension() == s)
+            .ok_or_else(|| format!("unknown data format `{}` (expected json or msgpack)", s))
+    }
+}
+
+/// Encodes `value` as MessagePack.
+#[cfg(feature = "msgpack")]
+pub fn to_msgpack<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::to_vec_named(value)
+}
+
+/// Decodes a value written by [`to_msgpack`].
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack<T: serde::de::DeserializeOwned>(
+    bytes: &[u8],
+) -> Result<T, rmp_serde::decode::Error> {
+    rmp_serde::from_slice(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_format_names() {
+        assert_eq!("msgpack".parse(), Ok(DataFormat::MessagePack));
+        assert!("yaml".parse::<DataFormat>().is_err());
+        assert_eq!(
+            DataFormat::from_mime_type("application/json; charset=utf-8"),
+            Some(DataFormat::Json)
+        );
+        assert_eq!(
+            DataFormat::from_mime_type("application/x-msgpack"),
+            Some(DataFormat::MessagePack)
+        );
+        assert_eq!(
+            DataFormat::from_path("/sinter_data/posts/a.3f9a0c1b2d.msgpack?v=1"),
+            Some(DataFormat::MessagePack)
+        );
+        assert_eq!(DataFormat::from_path("/sinter_data/posts/a"), None);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_round_trip() {
+        use crate::{ContentNode, LiteDate, Post, PostMetadata};
+
+        let post = Post {
+            metadata: PostMetadata {
+                id: "1".to_string(),
+                title: "Hello".to_string(),
+                slug: "hello".to_string(),
+                date: LiteDate {
+                    year: 2024,
+                    month: 5,
+                    day: 1,
+                },
+                tags: vec!["rust".to_string()],
+                tag_slugs: vec![],
+                draft: false,
+                summary: "Hi".to_string(),
+                word_count: 2,
+                reading_time: 1,
+            },
+            content_ast: vec![ContentNode::Paragraph {
+                children: vec![ContentNode::Text {
+                    value: "Hi there".to_string(),
+                }],
+            }],
+            toc: vec![],
+        };
+        let bytes = to_msgpack(&post).unwrap();
+        assert_eq!(from_msgpack::<Post>(&bytes).unwrap(), post);
+    }
+}
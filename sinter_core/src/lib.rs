@@ -1,8 +1,12 @@
+use encoding::DataFormat;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+pub mod encoding;
+pub mod search;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct LiteDate {
     pub year: i32,
     pub month: u8,
@@ -52,8 +56,42 @@ pub struct PostMetadata {
 
     #[serde(default)]
     pub tags: Vec<String>,
+    /// Slug of each entry of `tags`, in the same order. Filled in by the
+    /// compiler, which keeps distinct tags from sharing a slug.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag_slugs: Vec<String>,
+
+    /// Drafts are left out of builds unless drafts are requested.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub draft: bool,
 
+    /// Taken from the frontmatter, or generated from the post body by the
+    /// compiler when omitted.
+    #[serde(default)]
     pub summary: String,
+
+    /// Words in the post body, with every CJK character counted as a word.
+    /// Filled in by the compiler.
+    #[serde(default)]
+    pub word_count: usize,
+    /// Estimated reading time in whole minutes. Filled in by the compiler.
+    #[serde(default)]
+    pub reading_time: u32,
+}
+
+impl PostMetadata {
+    /// Each tag with the slug of its `/tags/<slug>` page, falling back to
+    /// [`tag_slug`] for metadata the compiler has not assigned slugs to.
+    pub fn tags_with_slugs(&self) -> impl Iterator<Item = (&str, String)> + '_ {
+        self.tags.iter().enumerate().map(|(i, tag)| {
+            let slug = self
+                .tag_slugs
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| tag_slug(tag));
+            (tag.as_str(), slug)
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -88,25 +126,65 @@ pub enum ContentNode {
     BlockQuote {
         children: Vec<ContentNode>,
     },
+    /// A `> [!NOTE]` style callout. `kind` is lowercase: one of GitHub's alert
+    /// kinds or a custom kind declared in the site config.
+    Admonition {
+        kind: String,
+        title: String,
+        children: Vec<ContentNode>,
+    },
+    /// Footnote body. Definitions are moved to the end of the document and
+    /// sorted by `number`.
+    FootnoteDefinition {
+        label: String,
+        number: usize,
+        /// Anchor id of this definition, e.g. `fn-1`.
+        id: String,
+        /// Anchor ids of every reference to this footnote, in document order.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        back_refs: Vec<String>,
+        children: Vec<ContentNode>,
+    },
 
     // Leaf nodes
     CodeBlock {
         lang: Option<String>,
         code_text: String,
+        /// `code_text` split into highlighted spans at build time; empty when
+        /// highlighting is off or the language is unknown.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tokens: Vec<CodeToken>,
     },
     Text {
         value: String,
     },
+    /// `` `code` `` inside prose.
+    InlineCode {
+        value: String,
+    },
     Html {
         value: String,
     },
     Math {
         value: String,
         display: bool,
+        /// `value` converted to a `<math>` element at build time; absent when
+        /// the expression uses TeX the converter does not support.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mathml: Option<String>,
     },
     TaskListMarker {
         checked: bool,
     },
+    /// `[^label]`. Footnotes are numbered in order of their first reference.
+    FootnoteReference {
+        label: String,
+        number: usize,
+        /// Anchor id of this reference, e.g. `fnref-1` or `fnref-1-2`.
+        id: String,
+        /// Anchor id of the definition.
+        target: String,
+    },
     ThematicBreak,
 
     // Inline formatting
@@ -130,10 +208,25 @@ pub enum ContentNode {
         url: String,
         title: Option<String>,
         alt: String,
+        /// Resized copies of a local image, largest last, for `srcset`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        srcset: Vec<ImageSource>,
+        /// Intrinsic size of a local image, so space is reserved before it loads.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<u32>,
+        /// Tiny preview of a local image as a data URI, shown blurred while the
+        /// image loads.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        placeholder: Option<String>,
     },
 
     // Table
     Table {
+        /// Alignment of each column, from the delimiter row.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        alignments: Vec<TableAlignment>,
         children: Vec<ContentNode>,
     },
     TableHead {
@@ -146,15 +239,121 @@ pub enum ContentNode {
         children: Vec<ContentNode>,
     },
     TableCell {
+        /// Set for the cells of the header row.
+        #[serde(default, skip_serializing_if = "is_false")]
+        header: bool,
+        /// The column's alignment, repeated so cells render without context.
+        #[serde(default, skip_serializing_if = "TableAlignment::is_none")]
+        align: TableAlignment,
         children: Vec<ContentNode>,
     },
 }
 
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// One candidate of an image's `srcset`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImageSource {
+    pub url: String,
+    /// Intrinsic width in pixels, the `w` descriptor.
+    pub width: u32,
+}
+
+/// Column alignment set by the `:---:` delimiter row of a table.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum TableAlignment {
+    #[default]
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+impl TableAlignment {
+    pub fn is_none(&self) -> bool {
+        *self == TableAlignment::None
+    }
+
+    /// Value for the CSS `text-align` property, if the column is aligned.
+    pub fn text_align(self) -> Option<&'static str> {
+        match self {
+            TableAlignment::None => None,
+            TableAlignment::Left => Some("left"),
+            TableAlignment::Center => Some("center"),
+            TableAlignment::Right => Some("right"),
+        }
+    }
+}
+
+/// A run of code text with a single syntax category.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CodeToken {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<TokenKind>,
+    pub text: String,
+}
+
+/// Syntax categories emitted by the build-time highlighter. Themes style them
+/// through the CSS class returned by [`TokenKind::class`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenKind {
+    Comment,
+    String,
+    Keyword,
+    Number,
+    Constant,
+    Operator,
+    Function,
+    Type,
+    Variable,
+    Tag,
+    Attribute,
+    Punctuation,
+}
+
+impl TokenKind {
+    pub fn class(self) -> &'static str {
+        match self {
+            TokenKind::Comment => "hl-comment",
+            TokenKind::String => "hl-string",
+            TokenKind::Keyword => "hl-keyword",
+            TokenKind::Number => "hl-number",
+            TokenKind::Constant => "hl-constant",
+            TokenKind::Operator => "hl-operator",
+            TokenKind::Function => "hl-function",
+            TokenKind::Type => "hl-type",
+            TokenKind::Variable => "hl-variable",
+            TokenKind::Tag => "hl-tag",
+            TokenKind::Attribute => "hl-attribute",
+            TokenKind::Punctuation => "hl-punctuation",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Post {
     #[serde(flatten)]
     pub metadata: PostMetadata,
     pub content_ast: Vec<ContentNode>,
+    /// Nested outline of the post's top-level headings.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub toc: Vec<TocEntry>,
+}
+
+/// One heading in a post's table of contents. `id` is the heading's anchor.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub title: String,
+    /// Deeper headings that follow this one, up to the next heading of the
+    /// same or a higher level.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TocEntry>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -167,12 +366,96 @@ pub struct SiteMetaData {
     #[serde(default)]
     pub description: String,
     pub total_pages: usize,
+    /// Path of the `DataManifest`, relative to the data directory, when shards
+    /// are written under content-hashed names.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    /// Encoding of the post and page shards.
+    #[serde(default, skip_serializing_if = "DataFormat::is_json")]
+    pub format: DataFormat,
+}
+
+/// Maps the fixed path of every data shard (`pages/page_1.json`) to the
+/// content-hashed name it was written under (`pages/page_1.3f9a0c1b2d.json`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct DataManifest {
+    pub files: BTreeMap<String, String>,
+}
+
+impl DataManifest {
+    /// The published path of `path`; paths not in the manifest are unchanged.
+    pub fn resolve<'a>(&'a self, path: &'a str) -> &'a str {
+        self.files.get(path).map(String::as_str).unwrap_or(path)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PageData {
     pub posts: Vec<SitePostMetadata>,
-    pub tags_index: HashMap<String, Vec<String>>,
+    pub tags_index: BTreeMap<String, Vec<String>>,
+}
+
+/// One entry of the global tag cloud (`tags/index.json`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TagSummary {
+    pub name: String,
+    /// URL-safe form of `name`, used for `/tags/<slug>` and the shard directory.
+    pub slug: String,
+    pub count: usize,
+    pub total_pages: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TagIndex {
+    pub tags: Vec<TagSummary>,
+}
+
+/// Turns a free-form label (e.g. a tag) into a URL path segment.
+///
+/// Letters and digits of any script are kept (lowercased), every other run of
+/// characters becomes a single `-`. Labels with no letters or digits at all are
+/// hex-encoded so they still map to a stable, non-empty segment.
+pub fn slugify(input: &str) -> String {
+    let mut slug = String::with_capacity(input.len());
+    for c in input.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+
+    if slug.is_empty() {
+        input.bytes().map(|b| format!("{:02x}", b)).collect()
+    } else {
+        slug
+    }
+}
+
+/// Route of page `page` of the listing at `base` (`/`, `/archives`,
+/// `/tags/<slug>`): the first page is `base` itself, later ones `<base>/page/N`.
+pub fn page_route(base: &str, page: usize) -> String {
+    match page {
+        0 | 1 => base.to_string(),
+        _ => format!("{}/page/{}", base.trim_end_matches('/'), page),
+    }
+}
+
+/// Like [`slugify`], but spells out `+` and `#` so that tags such as `C`,
+/// `C++` and `C#` get distinct slugs (`c`, `c-plus-plus`, `c-sharp`).
+pub fn tag_slug(tag: &str) -> String {
+    let mut spelled = String::with_capacity(tag.len());
+    for c in tag.trim().chars() {
+        match c {
+            '+' => spelled.push_str(" plus "),
+            '#' => spelled.push_str(" sharp "),
+            _ => spelled.push(c),
+        }
+    }
+    slugify(&spelled)
 }
 
 pub mod constants {
@@ -180,4 +463,38 @@ pub mod constants {
     pub const SITE_DATA_FILENAME: &str = "site_data.json";
     pub const PAGES_DIR: &str = "pages";
     pub const POSTS_DIR: &str = "posts";
+    pub const TAGS_DIR: &str = "tags";
+    pub const TAGS_INDEX_FILENAME: &str = "index.json";
+    pub const SEARCH_DIR: &str = "search";
+    pub const SEARCH_INDEX_FILENAME: &str = "index.json";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Rust"), "rust");
+        assert_eq!(slugify("  Web Assembly!! "), "web-assembly");
+        assert_eq!(slugify("C++"), "c");
+        assert_eq!(slugify("编程 笔记"), "编程-笔记");
+        assert_eq!(slugify("++"), "2b2b");
+    }
+
+    #[test]
+    fn test_page_route() {
+        assert_eq!(page_route("/", 1), "/");
+        assert_eq!(page_route("/", 2), "/page/2");
+        assert_eq!(page_route("/tags/rust", 3), "/tags/rust/page/3");
+    }
+
+    #[test]
+    fn test_tag_slug() {
+        assert_eq!(tag_slug("C"), "c");
+        assert_eq!(tag_slug("C++"), "c-plus-plus");
+        assert_eq!(tag_slug("C#"), "c-sharp");
+        assert_eq!(tag_slug(" Web Assembly "), "web-assembly");
+        assert_eq!(tag_slug("编程"), "编程");
+    }
 }
@@ -0,0 +1,217 @@
+//! Shared pieces of the full-text search index.
+//!
+//! The compiler writes one [`SearchManifest`] plus `shard_count` [`SearchShard`]s.
+//! Every term lives in exactly one shard (see [`shard_of`]), so the client only
+//! fetches the shards that contain its query terms. The compiler indexes text
+//! split by [`index_terms`] and the client looks up queries split by
+//! [`tokenize`], so every query term is also an index term.
+
+use crate::LiteDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// A post that can be returned as a search result.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchDoc {
+    pub title: String,
+    /// Client-side route of the post, e.g. `/posts/hello`.
+    pub url: String,
+    pub date: LiteDate,
+    pub summary: String,
+}
+
+/// `search/index.json`: everything the client needs before loading shards.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchManifest {
+    pub shard_count: u32,
+    pub cjk_bigrams: bool,
+    pub docs: Vec<SearchDoc>,
+}
+
+/// A `(doc, weight)` pair: the index of the post in [`SearchManifest::docs`] and
+/// the weighted number of occurrences of the term in it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Posting(pub u32, pub u32);
+
+/// `search/shard_{n}.json`: the postings of every term hashed to this shard.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SearchShard {
+    pub terms: BTreeMap<String, Vec<Posting>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub doc: SearchDoc,
+    pub score: f32,
+}
+
+/// Whether `c` belongs to a script written without spaces between words.
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'     // Hiragana, Katakana
+        | '\u{3400}'..='\u{4dbf}'   // CJK Extension A
+        | '\u{4e00}'..='\u{9fff}'   // CJK Unified Ideographs
+        | '\u{ac00}'..='\u{d7af}'   // Hangul Syllables
+        | '\u{f900}'..='\u{faff}'   // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{2ebef}' // CJK Extensions B-F
+    )
+}
+
+/// Splits a query into lowercase search terms.
+///
+/// Runs of letters and digits become one term each. CJK text has no word
+/// boundaries, so with `cjk_bigrams` every pair of adjacent CJK characters is a
+/// term (a lone character stays a unigram); without it every character is one.
+pub fn tokenize(text: &str, cjk_bigrams: bool) -> Vec<String> {
+    split_terms(text, cjk_bigrams, false)
+}
+
+/// Splits indexed text into terms. Same as [`tokenize`], except that with
+/// `cjk_bigrams` every CJK character is also indexed on its own, so that
+/// one-character queries find the bigrams' text too.
+pub fn index_terms(text: &str, cjk_bigrams: bool) -> Vec<String> {
+    split_terms(text, cjk_bigrams, true)
+}
+
+fn split_terms(text: &str, cjk_bigrams: bool, with_unigrams: bool) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    let mut cjk_run: Vec<char> = Vec::new();
+
+    let flush_cjk = |run: &mut Vec<char>, terms: &mut Vec<String>| {
+        if cjk_bigrams && run.len() > 1 {
+            terms.extend(run.windows(2).map(|pair| pair.iter().collect()));
+            if with_unigrams {
+                terms.extend(run.iter().map(|c| c.to_string()));
+            }
+        } else {
+            terms.extend(run.iter().map(|c| c.to_string()));
+        }
+        run.clear();
+    };
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+            cjk_run.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk_run, &mut terms);
+            word.extend(c.to_lowercase());
+        } else {
+            flush_cjk(&mut cjk_run, &mut terms);
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+        }
+    }
+    flush_cjk(&mut cjk_run, &mut terms);
+    if !word.is_empty() {
+        terms.push(word);
+    }
+    terms
+}
+
+/// The shard a term is stored in. Uses FNV-1a so the result is identical on
+/// the compiler and in WASM.
+pub fn shard_of(term: &str, shard_count: u32) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in term.bytes() {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash % shard_count.max(1)
+}
+
+/// Ranks the documents matching `terms`, best first.
+///
+/// Documents matching more distinct query terms always rank higher; ties are
+/// broken by a TF-IDF score. `lookup` returns the postings of a term, or `None`
+/// if the term is not in the index.
+pub fn rank<'a, F>(manifest: &SearchManifest, terms: &[String], lookup: F) -> Vec<SearchHit>
+where
+    F: Fn(&str) -> Option<&'a [Posting]>,
+{
+    let total_docs = manifest.docs.len() as f32;
+    let mut unique: Vec<&str> = terms.iter().map(String::as_str).collect();
+    unique.sort_unstable();
+    unique.dedup();
+
+    // doc -> (matched terms, score)
+    let mut scores: HashMap<u32, (usize, f32)> = HashMap::new();
+    for term in unique {
+        let Some(postings) = lookup(term) else {
+            continue;
+        };
+        let idf = (1.0 + total_docs / postings.len().max(1) as f32).ln();
+        for &Posting(doc, weight) in postings {
+            let entry = scores.entry(doc).or_insert((0, 0.0));
+            entry.0 += 1;
+            entry.1 += weight as f32 * idf;
+        }
+    }
+
+    let mut ranked: Vec<_> = scores
+        .into_iter()
+        .filter(|(doc, _)| (*doc as usize) < manifest.docs.len())
+        .collect();
+    ranked.sort_by(
+        |(doc_a, (matched_a, score_a)), (doc_b, (matched_b, score_b))| {
+            matched_b
+                .cmp(matched_a)
+                .then(score_b.total_cmp(score_a))
+                .then(doc_a.cmp(doc_b))
+        },
+    );
+
+    ranked
+        .into_iter()
+        .map(|(doc, (_, score))| SearchHit {
+            doc: manifest.docs[doc as usize].clone(),
+            score,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_mixed_text() {
+        assert_eq!(
+            tokenize("Rust 编程语言, WASM!", true),
+            ["rust", "编程", "程语", "语言", "wasm"]
+        );
+        assert_eq!(tokenize("学Rust", false), ["学", "rust"]);
+        assert_eq!(index_terms("锈蚀", true), ["锈蚀", "锈", "蚀"]);
+    }
+
+    #[test]
+    fn test_rank_prefers_documents_matching_all_terms() {
+        let doc = |title: &str| SearchDoc {
+            title: title.to_string(),
+            url: format!("/posts/{}", title),
+            date: LiteDate {
+                year: 2024,
+                month: 1,
+                day: 1,
+            },
+            summary: String::new(),
+        };
+        let manifest = SearchManifest {
+            shard_count: 1,
+            cjk_bigrams: true,
+            docs: vec![doc("a"), doc("b")],
+        };
+        let mut index = BTreeMap::new();
+        index.insert("rust".to_string(), vec![Posting(0, 10), Posting(1, 1)]);
+        index.insert("wasm".to_string(), vec![Posting(1, 1)]);
+
+        let terms = tokenize("rust wasm", true);
+        let hits = rank(&manifest, &terms, |t| index.get(t).map(Vec::as_slice));
+        let titles: Vec<_> = hits.iter().map(|h| h.doc.title.as_str()).collect();
+        assert_eq!(titles, ["b", "a"]);
+    }
+}
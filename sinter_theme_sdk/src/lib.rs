@@ -1,4 +1,5 @@
-use sinter_core::{PageData, Post, SiteMetaData};
+use sinter_core::encoding::DataFormat;
+use sinter_core::{PageData, Post, SiteMetaData, TagIndex};
 use sinter_ui::dom::view::AnyView;
 use sinter_ui::prelude::*;
 use std::collections::HashMap;
@@ -7,7 +8,15 @@ use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{HtmlLinkElement, Response, window};
 
-// Helper for fetching JSON
+mod manifest;
+mod search;
+pub use manifest::{data_url, shard_url};
+pub use search::{search, use_search};
+
+// Helper for fetching data shards. The format is fixed at build time and
+// chosen through the URL (see `shard_url`); the response is decoded according
+// to its `Content-Type`, or its extension when the host labels it
+// `application/octet-stream`.
 pub async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, String> {
     let window = window().ok_or("No global window")?;
     let resp_value = JsFuture::from(window.fetch_with_str(url))
@@ -26,6 +35,18 @@ pub async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T,
         ));
     }
 
+    let format = resp
+        .headers()
+        .get("Content-Type")
+        .ok()
+        .flatten()
+        .and_then(|content_type| DataFormat::from_mime_type(&content_type))
+        .or_else(|| DataFormat::from_path(url))
+        .unwrap_or_default();
+    if format == DataFormat::MessagePack {
+        return decode_msgpack(&resp).await;
+    }
+
     let json_value = JsFuture::from(resp.json().map_err(|e| format!("json error: {:?}", e))?)
         .await
         .map_err(|e| format!("json await error: {:?}", e))?;
@@ -33,12 +54,33 @@ pub async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T,
     serde_wasm_bindgen::from_value(json_value).map_err(|e| format!("Deserialization error: {}", e))
 }
 
+#[cfg(feature = "msgpack")]
+async fn decode_msgpack<T: serde::de::DeserializeOwned>(resp: &Response) -> Result<T, String> {
+    let buffer = JsFuture::from(
+        resp.array_buffer()
+            .map_err(|e| format!("array_buffer error: {:?}", e))?,
+    )
+    .await
+    .map_err(|e| format!("array_buffer await error: {:?}", e))?;
+    let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+    sinter_core::encoding::from_msgpack(&bytes).map_err(|e| format!("Deserialization error: {}", e))
+}
+
+#[cfg(not(feature = "msgpack"))]
+async fn decode_msgpack<T: serde::de::DeserializeOwned>(_resp: &Response) -> Result<T, String> {
+    Err("Received MessagePack data, but the `msgpack` feature is disabled".to_string())
+}
+
 pub type Children = Arc<dyn Fn() -> AnyView>;
 
 pub trait Theme: Send + Sync + std::fmt::Debug {
     fn render_home(&self) -> AnyView;
     fn render_archive(&self) -> AnyView;
     fn render_post(&self, post: Post) -> AnyView;
+    /// Listing of every post carrying the tag whose slug is `tag`.
+    fn render_tag(&self, tag: String) -> AnyView;
+    /// Search page; `query` is the initial `?q=` value and may be empty.
+    fn render_search(&self, query: String) -> AnyView;
     fn render_post_loading(&self) -> AnyView;
     fn render_loading(&self) -> AnyView;
     fn render_post_not_found(&self) -> AnyView;
@@ -132,15 +174,31 @@ impl ThemeManager {
 }
 
 pub async fn fetch_site_meta() -> Result<SiteMetaData, String> {
-    fetch_json("/sinter_data/site_data.json").await
+    manifest::load_site_meta().await.map(|meta| (*meta).clone())
 }
 
 pub async fn fetch_page_data(page: usize) -> Result<PageData, String> {
-    fetch_json(&format!("/sinter_data/pages/page_{}.json", page)).await
+    fetch_json(&shard_url(&format!("pages/page_{}", page)).await?).await
 }
 
 pub async fn fetch_archive_page_data(page: usize) -> Result<PageData, String> {
-    fetch_json(&format!("/sinter_data/archives/pages/page_{}.json", page)).await
+    fetch_json(&shard_url(&format!("archives/pages/page_{}", page)).await?).await
+}
+
+pub async fn fetch_tag_index() -> Result<TagIndex, String> {
+    fetch_json(&data_url("tags/index.json").await?).await
+}
+
+pub async fn fetch_tag_page_data(tag: &str, page: usize) -> Result<PageData, String> {
+    fetch_json(&shard_url(&format!("tags/{}/page_{}", tag, page)).await?).await
+}
+
+pub async fn fetch_post(slug: &str) -> Result<Post, String> {
+    fetch_json(&shard_url(&format!("posts/{}", slug)).await?).await
+}
+
+pub async fn fetch_archive_post(slug: &str) -> Result<Post, String> {
+    fetch_json(&shard_url(&format!("archives/{}", slug)).await?).await
 }
 
 #[derive(Clone)]
@@ -199,6 +257,14 @@ pub fn use_site_meta() -> Option<Resource<Result<SiteMetaData, String>>> {
     use_context::<GlobalState>().map(|state| state.site_meta)
 }
 
+// Provided by the tag routes only, so other pages never fetch `tags/index.json`.
+#[derive(Clone, Copy)]
+pub struct TagIndexContext(pub Resource<Result<TagIndex, String>>);
+
+pub fn use_tag_index() -> Option<Resource<Result<TagIndex, String>>> {
+    use_context::<TagIndexContext>().map(|ctx| ctx.0)
+}
+
 // Ensure you provide this context in your page component!
 #[derive(Clone, Copy)]
 pub struct PageDataContext(pub Resource<Result<PageData, String>>);
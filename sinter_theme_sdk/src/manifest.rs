@@ -0,0 +1,52 @@
+use crate::fetch_json;
+use sinter_core::constants::SITE_DATA_FILENAME;
+use sinter_core::{DataManifest, SiteMetaData};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const DATA_URL_PATH: &str = "/sinter_data";
+
+// Both files describe a single build, so they are fetched at most once. When
+// the site is built with hashed shard names, every other data URL is looked up
+// in the manifest.
+thread_local! {
+    static SITE_META: RefCell<Option<Rc<SiteMetaData>>> = const { RefCell::new(None) };
+    static MANIFEST: RefCell<Option<Rc<DataManifest>>> = const { RefCell::new(None) };
+}
+
+pub(crate) async fn load_site_meta() -> Result<Rc<SiteMetaData>, String> {
+    if let Some(meta) = SITE_META.with(|m| m.borrow().clone()) {
+        return Ok(meta);
+    }
+    let url = format!("{}/{}", DATA_URL_PATH, SITE_DATA_FILENAME);
+    let meta = Rc::new(fetch_json::<SiteMetaData>(&url).await?);
+    SITE_META.with(|m| *m.borrow_mut() = Some(meta.clone()));
+    Ok(meta)
+}
+
+async fn load_manifest() -> Result<Rc<DataManifest>, String> {
+    if let Some(manifest) = MANIFEST.with(|m| m.borrow().clone()) {
+        return Ok(manifest);
+    }
+    let manifest = match &load_site_meta().await?.manifest {
+        Some(path) => fetch_json(&format!("{}/{}", DATA_URL_PATH, path)).await?,
+        None => DataManifest::default(),
+    };
+    let manifest = Rc::new(manifest);
+    MANIFEST.with(|m| *m.borrow_mut() = Some(manifest.clone()));
+    Ok(manifest)
+}
+
+/// URL of the data file at `path` (relative to the data directory, e.g.
+/// `pages/page_1.json`), under its content-hashed name if it has one.
+pub async fn data_url(path: &str) -> Result<String, String> {
+    let manifest = load_manifest().await?;
+    Ok(format!("{}/{}", DATA_URL_PATH, manifest.resolve(path)))
+}
+
+/// URL of the post or page shard at `stem` (e.g. `pages/page_1`), with the
+/// extension of the format the site was built with.
+pub async fn shard_url(stem: &str) -> Result<String, String> {
+    let format = load_site_meta().await?.format;
+    data_url(&format!("{}.{}", stem, format.extension())).await
+}
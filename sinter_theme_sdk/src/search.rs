@@ -0,0 +1,69 @@
+use crate::{data_url, fetch_json};
+use sinter_core::search::{SearchHit, SearchManifest, SearchShard, rank, shard_of, tokenize};
+use sinter_ui::prelude::*;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+
+// The manifest and shards never change while the app is running, so they are
+// fetched at most once and shared by every search.
+thread_local! {
+    static MANIFEST: RefCell<Option<Rc<SearchManifest>>> = const { RefCell::new(None) };
+    static SHARDS: RefCell<HashMap<u32, Rc<SearchShard>>> = RefCell::new(HashMap::new());
+}
+
+async fn load_manifest() -> Result<Rc<SearchManifest>, String> {
+    if let Some(manifest) = MANIFEST.with(|m| m.borrow().clone()) {
+        return Ok(manifest);
+    }
+    let url = data_url("search/index.json").await?;
+    let manifest = Rc::new(fetch_json::<SearchManifest>(&url).await?);
+    MANIFEST.with(|m| *m.borrow_mut() = Some(manifest.clone()));
+    Ok(manifest)
+}
+
+async fn load_shard(id: u32) -> Result<Rc<SearchShard>, String> {
+    if let Some(shard) = SHARDS.with(|s| s.borrow().get(&id).cloned()) {
+        return Ok(shard);
+    }
+    let url = data_url(&format!("search/shard_{}.json", id)).await?;
+    let shard = Rc::new(fetch_json::<SearchShard>(&url).await?);
+    SHARDS.with(|s| s.borrow_mut().insert(id, shard.clone()));
+    Ok(shard)
+}
+
+/// Runs `query` against the compiled search index, fetching only the shards
+/// that hold its terms. An empty query returns no results without any request.
+pub async fn search(query: &str) -> Result<Vec<SearchHit>, String> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let manifest = load_manifest().await?;
+    let terms = tokenize(query, manifest.cjk_bigrams);
+
+    let needed: BTreeSet<u32> = terms
+        .iter()
+        .map(|term| shard_of(term, manifest.shard_count))
+        .collect();
+    let mut shards = HashMap::new();
+    for id in needed {
+        shards.insert(id, load_shard(id).await?);
+    }
+
+    Ok(rank(&manifest, &terms, |term| {
+        shards
+            .get(&shard_of(term, manifest.shard_count))
+            .and_then(|shard| shard.terms.get(term))
+            .map(Vec::as_slice)
+    }))
+}
+
+/// Search results for `query`, re-ranked whenever the signal changes.
+pub fn use_search(query: ReadSignal<String>) -> Resource<Result<Vec<SearchHit>, String>> {
+    create_resource(
+        move || query.get().unwrap_or_default(),
+        |q| async move { search(&q).await },
+    )
+    .expect("Failed to create resource")
+}
@@ -1,4 +1,8 @@
-use sinter_core::{ContentNode, Post, SiteMetaData, SitePostMetadata};
+use sinter_core::search::SearchHit;
+use sinter_core::{
+    CodeToken, ContentNode, Post, SiteMetaData, SitePostMetadata, TagSummary, TocEntry,
+    page_route,
+};
 use sinter_theme_sdk::{Children, Theme};
 use sinter_ui::dom::tag::*;
 use sinter_ui::dom::suspense::suspense;
@@ -190,7 +194,7 @@ impl Theme for DefaultLightTheme {
                                         |post| render_post_card(post, false) // false for home
                                     ),
                                     // Pagination Controls
-                                    render_pagination(search, total_pages, false)
+                                    render_pagination(search, total_pages, "/")
                                 ))
                             )
                         ))
@@ -271,7 +275,7 @@ impl Theme for DefaultLightTheme {
                                         |post| render_post_card(post, true) // true for archive
                                     ),
                                     // Pagination Controls
-                                    render_pagination(search, total_pages, true)
+                                    render_pagination(search, total_pages, "/archives")
                                 ))
                             )
                         ))
@@ -285,6 +289,12 @@ impl Theme for DefaultLightTheme {
 
     fn render_post(&self, post: Post) -> AnyView {
         let content_ast = post.content_ast.clone();
+        let toc_class = if post.toc.is_empty() {
+            "hidden"
+        } else {
+            "hidden xl:block absolute top-0 left-full h-full ml-16 w-56 z-10"
+        };
+        let toc = post.toc.clone();
         
         div()
             .class("pt-24 lg:pt-32 pb-20 px-4")
@@ -302,11 +312,13 @@ impl Theme for DefaultLightTheme {
                                 div().class("flex flex-wrap items-center justify-center gap-4 text-sm font-medium text-slate-600").child((
                                     time().class("px-4 py-1.5 rounded-full bg-white/40 border border-slate-200 backdrop-blur-sm")
                                         .text(format_date_long(&post.metadata.date)),
+                                    span().class(if post.metadata.reading_time > 0 { "px-4 py-1.5 rounded-full bg-white/40 border border-slate-200 backdrop-blur-sm" } else { "hidden" })
+                                        .text(format!("{} min read", post.metadata.reading_time)),
                                     div().class("flex gap-2").child(
                                         For::new(
-                                            move || Ok(post.metadata.tags.clone()),
-                                            |tag| tag.clone(),
-                                            |tag| span().class("px-3 py-1 rounded-full bg-primary/10 text-primary border border-primary/10 backdrop-blur-sm uppercase tracking-wider text-xs").text(tag)
+                                            move || Ok(post.metadata.tags_with_slugs().map(|(tag, slug)| (tag.to_string(), slug)).collect::<Vec<_>>()),
+                                            |(tag, _)| tag.clone(),
+                                            |(tag, slug)| a().attr("href", format!("/tags/{}", slug)).class("px-3 py-1 rounded-full bg-primary/10 text-primary border border-primary/10 backdrop-blur-sm uppercase tracking-wider text-xs").text(tag)
                                         )
                                     )
                                 ))
@@ -329,12 +341,110 @@ impl Theme for DefaultLightTheme {
                                         "Back to Home"
                                     ))
                             )
-                        ))
+                        )),
+                        // Table of contents, pinned beside the article on wide screens
+                        aside().class(toc_class).child(
+                            nav().id("toc").class("sticky top-32").attr("aria-label", "Table of contents").child((
+                                p().class("text-xs font-bold uppercase tracking-widest text-slate-500 mb-4").text("Contents"),
+                                render_toc(toc),
+                            ))
+                        ),
                     ))
             )
             .into_any()
     }
 
+    fn render_tag(&self, tag: String) -> AnyView {
+        let tag_index_r = sinter_theme_sdk::use_tag_index();
+        let page_data_r = sinter_theme_sdk::use_page_data();
+        let current_page_s = sinter_theme_sdk::use_current_page();
+
+        let theme_fallback = self.clone();
+        let theme_fallback_clone = theme_fallback.clone();
+
+        suspense()
+            .fallback(move || theme_fallback.render_loading())
+            .children(move || {
+                let tag_index_res = tag_index_r.and_then(|r| r.get()).and_then(|r| r.ok());
+                let page_data_res = page_data_r.and_then(|r| r.get().and_then(|res| res.ok()));
+
+                let Some(tag_index) = tag_index_res else {
+                    return theme_fallback_clone.render_loading();
+                };
+                let Some(summary) = tag_index.tags.iter().find(|t| t.slug == tag).cloned() else {
+                    return theme_fallback_clone.render_post_not_found();
+                };
+                let Some(page_data) = page_data_res else {
+                    return theme_fallback_clone.render_loading();
+                };
+
+                let posts = page_data.posts;
+                let search = current_page_s.get().unwrap_or(1);
+                let base_url = format!("/tags/{}", summary.slug);
+                let current_slug = summary.slug.clone();
+
+                div().class("pt-24 lg:pt-32 pb-20 px-4 min-h-[50vh]").child(
+                    div().class("container mx-auto max-w-5xl").child((
+                        header().class("text-center mb-16 space-y-4 animate-fade-in").child((
+                            h1().class("text-4xl md:text-6xl font-black tracking-tighter text-slate-900 drop-shadow-sm").text(format!("# {}", summary.name)),
+                            p().class("text-lg font-medium text-slate-600").text(format!("{} post(s)", summary.count)),
+                            div().class("flex flex-wrap justify-center gap-2 pt-4").child(
+                                tag_index.tags.into_iter().map(move |t| render_tag_chip(t, &current_slug)).collect::<Vec<_>>()
+                            )
+                        )),
+                        For::new(
+                            move || Ok(posts.clone()),
+                            |post| post.metadata.id.clone(),
+                            |post| {
+                                let is_archive = post.path.starts_with("archives/");
+                                render_post_card(post, is_archive)
+                            }
+                        ),
+                        render_pagination(search, summary.total_pages, &base_url)
+                    ))
+                ).into_any()
+            })
+            .into_any()
+    }
+
+    fn render_search(&self, query: String) -> AnyView {
+        let (query_s, set_query) = create_signal(query.clone());
+        let results_r = sinter_theme_sdk::use_search(query_s);
+        let theme = self.clone();
+
+        div().class("pt-24 lg:pt-32 pb-20 px-4 min-h-[50vh]").child(
+            div().class("container mx-auto max-w-3xl").child((
+                header().class("text-center mb-12 space-y-8 animate-fade-in").child((
+                    h1().class("text-4xl md:text-6xl font-black tracking-tighter text-slate-900 drop-shadow-sm").text("Search"),
+                    input()
+                        .attr("type", "search")
+                        .attr("placeholder", "Search posts…")
+                        .attr("value", query)
+                        .attr("autofocus", "")
+                        .class("input input-lg w-full rounded-full bg-white/60 border border-slate-200 text-slate-900 placeholder:text-slate-400 backdrop-blur-md focus:outline-none focus:border-slate-400")
+                        .on_input(move |value| {
+                            set_query.set(value);
+                        })
+                )),
+                // Only the result list re-renders, so the input keeps focus while typing.
+                Dynamic::new(move || {
+                    let has_query = !query_s.get().unwrap_or_default().trim().is_empty();
+                    match results_r.get() {
+                        None if has_query => theme.render_loading(),
+                        None => div().into_any(),
+                        Some(Err(e)) => theme.render_error(e),
+                        Some(Ok(hits)) if hits.is_empty() && has_query => {
+                            p().class("text-center text-slate-600").text("No matching posts.").into_any()
+                        }
+                        Some(Ok(hits)) => div().class("space-y-6").child(
+                            hits.into_iter().map(render_search_hit).collect::<Vec<_>>()
+                        ).into_any(),
+                    }
+                })
+            ))
+        ).into_any()
+    }
+
     fn render_post_loading(&self) -> AnyView {
         div()
             .class("flex justify-center items-center min-h-screen pt-20")
@@ -399,7 +509,8 @@ where
                 div().class("flex-none hidden md:block").child(
                     ul().class("menu menu-horizontal px-1 font-medium text-slate-700").child((
                         li().child(a().attr("href", "/").class("hover:bg-black/5 hover:text-slate-900 transition-all rounded-lg").text("Home")),
-                        li().child(a().attr("href", "/archives").class("hover:bg-black/5 hover:text-slate-900 transition-all rounded-lg").text("Archives"))
+                        li().child(a().attr("href", "/archives").class("hover:bg-black/5 hover:text-slate-900 transition-all rounded-lg").text("Archives")),
+                        li().child(a().attr("href", "/search").class("hover:bg-black/5 hover:text-slate-900 transition-all rounded-lg").text("Search"))
                     ))
                 ),
                 div().class("flex-none").child(
@@ -454,10 +565,9 @@ fn render_footer() -> Element {
         )
 }
 
-fn render_pagination(current_page: usize, total_pages: usize, is_archive: bool) -> Element {
-    let base_url = if is_archive { "/archives" } else { "/" };
-    let prev_url = format!("{}?page={}", base_url, current_page - 1);
-    let next_url = format!("{}?page={}", base_url, current_page + 1);
+fn render_pagination(current_page: usize, total_pages: usize, base_url: &str) -> Element {
+    let prev_url = page_route(base_url, current_page - 1);
+    let next_url = page_route(base_url, current_page + 1);
 
     div().class("flex justify-center items-center gap-4 mt-16 text-slate-700").child((
         if current_page > 1 {
@@ -490,6 +600,25 @@ fn render_pagination(current_page: usize, total_pages: usize, is_archive: bool)
     ))
 }
 
+fn render_search_hit(hit: SearchHit) -> Element {
+    a().attr("href", hit.doc.url).class("block p-6 rounded-2xl bg-white/60 border border-white/50 backdrop-blur-md shadow-lg hover:bg-white/80 transition-colors").child((
+        h2().class("text-2xl font-bold text-slate-900 mb-2").text(hit.doc.title),
+        div().class("text-sm text-slate-500 mb-3").text(format_date_slash(&hit.doc.date)),
+        p().class("text-slate-700 line-clamp-2").text(hit.doc.summary)
+    ))
+}
+
+fn render_tag_chip(tag: TagSummary, current_slug: &str) -> Element {
+    let class = if tag.slug == current_slug {
+        "px-3 py-1 rounded-full bg-primary/10 text-primary border border-primary/20 text-sm"
+    } else {
+        "px-3 py-1 rounded-full bg-slate-200/50 text-slate-700 border border-slate-200 hover:bg-slate-200 transition-colors text-sm"
+    };
+    a().attr("href", format!("/tags/{}", tag.slug))
+        .class(class)
+        .text(format!("{} ({})", tag.name, tag.count))
+}
+
 fn render_post_card(post: SitePostMetadata, is_archive: bool) -> Element {
     let link_base = if is_archive { "/archives/posts/" } else { "/posts/" };
     let slug = post.metadata.slug.clone();
@@ -509,10 +638,16 @@ fn render_post_card(post: SitePostMetadata, is_archive: bool) -> Element {
                     ),
                     span().text(format_date_slash(&post.metadata.date))
                 )),
+                div().class(if post.metadata.reading_time > 0 { "flex items-center gap-1" } else { "hidden" }).child((
+                    svg().class("h-4 w-4").attr("opacity", "0.7").attr("fill", "none").attr("viewBox", "0 0 24 24").attr("stroke", "currentColor").child(
+                        path().attr("stroke-linecap", "round").attr("stroke-linejoin", "round").attr("stroke-width", "2").attr("d", "M12 8v4l3 3m6-3a9 9 0 11-18 0 9 9 0 0118 0z")
+                    ),
+                    span().text(format!("{} min read", post.metadata.reading_time))
+                )),
                 div().class("hidden sm:block opacity-50").text("•"),
                 div().class("flex items-center gap-2").child(
-                    post.metadata.tags.iter().map(|tag| {
-                        span().class("px-2 py-0.5 rounded-full bg-slate-200/50 text-slate-700 border border-slate-200").text(tag.clone())
+                    post.metadata.tags_with_slugs().map(|(tag, slug)| {
+                        a().attr("href", format!("/tags/{}", slug)).class("px-2 py-0.5 rounded-full bg-slate-200/50 text-slate-700 border border-slate-200").text(tag.to_string())
                     }).collect::<Vec<_>>()
                 )
             )),
@@ -526,6 +661,58 @@ fn render_post_card(post: SitePostMetadata, is_archive: bool) -> Element {
     ))
 }
 
+/// Code block body, styled with the build-time highlight tokens when present.
+fn render_code(code_text: String, tokens: Vec<CodeToken>) -> AnyView {
+    if tokens.is_empty() {
+        return code().text(code_text).into_any();
+    }
+    code()
+        .child(
+            tokens
+                .into_iter()
+                .map(|token| match token.kind {
+                    Some(kind) => span().class(kind.class()).text(token.text).into_any(),
+                    None => span().text(token.text).into_any(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_any()
+}
+
+/// Nested list of heading links. Each link carries `data-toc-id` so a
+/// scrollspy can find the entry for the heading in view.
+fn render_toc(entries: Vec<TocEntry>) -> Element {
+    ol().class("space-y-1 text-sm").child(
+        entries
+            .into_iter()
+            .map(|entry| {
+                let link = a()
+                    .attr("href", format!("#{}", entry.id))
+                    .attr("data-toc-id", entry.id)
+                    .class("block py-1 text-slate-600 hover:text-primary transition-colors")
+                    .text(entry.title);
+                if entry.children.is_empty() {
+                    li().child(link).into_any()
+                } else {
+                    li().child((link, render_toc(entry.children).class("pl-4"))).into_any()
+                }
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Frame and title classes of an admonition; custom kinds use the primary colour.
+fn admonition_classes(kind: &str) -> (&'static str, &'static str) {
+    match kind {
+        "note" => ("border-sky-500 bg-sky-50", "text-sky-700"),
+        "tip" => ("border-emerald-500 bg-emerald-50", "text-emerald-700"),
+        "important" => ("border-violet-500 bg-violet-50", "text-violet-700"),
+        "warning" => ("border-amber-500 bg-amber-50", "text-amber-700"),
+        "caution" => ("border-rose-500 bg-rose-50", "text-rose-700"),
+        _ => ("border-primary bg-primary/5", "text-primary"),
+    }
+}
+
 fn render_node(node: ContentNode) -> AnyView {
     match node {
         ContentNode::Paragraph { children } => p()
@@ -576,7 +763,54 @@ fn render_node(node: ContentNode) -> AnyView {
             .class("border-l-4 border-primary/50 pl-6 py-4 italic bg-slate-100 rounded-r-lg my-8 text-slate-700")
             .child(children.into_iter().map(render_node).collect::<Vec<_>>())
             .into_any(),
-        ContentNode::CodeBlock { lang, code_text } => {
+        ContentNode::Admonition {
+            kind,
+            title,
+            children,
+        } => {
+            let (frame, accent) = admonition_classes(&kind);
+            div()
+                .class(format!("admonition admonition-{kind} border-l-4 rounded-r-lg px-5 py-4 my-8 {frame}"))
+                .attr("role", "note")
+                .child((
+                    p().class(format!("font-semibold mb-2 {accent}")).text(title),
+                    div()
+                        .class("[&>p:last-child]:mb-0")
+                        .child(children.into_iter().map(render_node).collect::<Vec<_>>()),
+                ))
+                .into_any()
+        }
+        ContentNode::FootnoteDefinition {
+            number,
+            id,
+            back_refs,
+            children,
+            ..
+        } => div()
+            .id(id)
+            .class("footnote flex gap-3 text-sm text-slate-600 border-t border-slate-200 pt-4 mt-4 first-of-type:mt-12")
+            .child((
+                span().class("font-mono text-primary").text(format!("{number}.")),
+                div()
+                    .class("flex-1 [&>p]:mb-2")
+                    .child(children.into_iter().map(render_node).collect::<Vec<_>>()),
+                back_refs
+                    .into_iter()
+                    .map(|back_ref| {
+                        a().attr("href", format!("#{back_ref}"))
+                            .attr("aria-label", "Back to reference")
+                            .class("link link-primary no-underline")
+                            .text("\u{21a9}")
+                            .into_any()
+                    })
+                    .collect::<Vec<_>>(),
+            ))
+            .into_any(),
+        ContentNode::CodeBlock {
+            lang,
+            code_text,
+            tokens,
+        } => {
             let lang_label = lang.unwrap_or_else(|| "text".to_string());
             div()
                 .class("code-block relative group my-8 rounded-xl overflow-hidden bg-slate-50 text-slate-800 shadow-lg border border-slate-200")
@@ -593,7 +827,7 @@ fn render_node(node: ContentNode) -> AnyView {
                             ))
                     )),
                     pre().class("p-6 overflow-x-auto font-mono text-sm leading-relaxed !bg-slate-50 !m-0 !rounded-none").child(
-                        code().text(code_text)
+                        render_code(code_text, tokens)
                     )
                 ))
                 .into_any()
@@ -603,7 +837,22 @@ fn render_node(node: ContentNode) -> AnyView {
             d.dom_element.set_inner_html(&value);
             d.into_any()
         }
-        ContentNode::Math { value, display } => {
+        ContentNode::Math {
+            display,
+            mathml: Some(mathml),
+            ..
+        } => {
+            if display {
+                let d = div().class("math block my-6 overflow-x-auto text-center");
+                d.dom_element.set_inner_html(&mathml);
+                d.into_any()
+            } else {
+                let s = span().class("math inline");
+                s.dom_element.set_inner_html(&mathml);
+                s.into_any()
+            }
+        }
+        ContentNode::Math { value, display, .. } => {
             let classes = format!(
                 "math {} bg-slate-100 px-1.5 py-0.5 rounded border border-slate-200 text-slate-900",
                 if display {
@@ -614,6 +863,17 @@ fn render_node(node: ContentNode) -> AnyView {
             );
             code().class(classes).text(format!("$ {value} $")).into_any()
         }
+        ContentNode::FootnoteReference {
+            number, id, target, ..
+        } => sup()
+            .id(id)
+            .class("footnote-ref")
+            .child(
+                a().attr("href", format!("#{target}"))
+                    .class("link link-primary no-underline")
+                    .text(format!("[{number}]")),
+            )
+            .into_any(),
         ContentNode::TaskListMarker { checked } => input()
             .attr("type", "checkbox")
             .attr("checked", checked.to_string())
@@ -621,6 +881,10 @@ fn render_node(node: ContentNode) -> AnyView {
             .class("checkbox checkbox-primary checkbox-xs mr-2 align-middle")
             .into_any(),
         ContentNode::Text { value } => span().class("text-inherit").text(value).into_any(),
+        ContentNode::InlineCode { value } => code()
+            .class("font-mono text-[0.9em] bg-slate-100 text-primary px-1.5 py-0.5 rounded border border-slate-200")
+            .text(value)
+            .into_any(),
         ContentNode::ThematicBreak => hr().class("my-12 border-slate-200").into_any(),
         ContentNode::Emphasis { children } => em()
             .class("italic text-slate-700")
@@ -644,14 +908,21 @@ fn render_node(node: ContentNode) -> AnyView {
             .class("link link-primary hover:text-primary-focus transition-colors decoration-2 decoration-primary/30 hover:decoration-primary")
             .child(children.into_iter().map(render_node).collect::<Vec<_>>())
             .into_any(),
-        ContentNode::Image { url, title, alt } => figure()
+        ContentNode::Image { url, title, alt, srcset, width, height, placeholder } => figure()
             .class("my-10")
             .child((
                 img()
                     .attr("src", url)
                     .attr("alt", alt)
                     .attr("title", title.clone().unwrap_or_default())
-                    .class("rounded-xl shadow-lg mx-auto max-w-full border border-slate-200")
+                    // Only set when known: empty values are not "unset" to the browser.
+                    .attr("srcset", (!srcset.is_empty()).then(|| srcset.iter().map(|s| format!("{} {}w", s.url, s.width)).collect::<Vec<_>>().join(", ")))
+                    .attr("sizes", (!srcset.is_empty()).then_some("(min-width: 56rem) 56rem, 100vw"))
+                    .attr("width", width.map(|w| w.to_string()))
+                    .attr("height", height.map(|h| h.to_string()))
+                    // Blurred preview painted behind the image until it has loaded.
+                    .style(placeholder.map(|p| format!("background-image: url({}); background-size: cover", p)).unwrap_or_default())
+                    .class("h-auto rounded-xl shadow-lg mx-auto max-w-full border border-slate-200")
                     .attr("loading", "lazy"),
                if let Some(t) = title {
                    figcaption().class("text-center text-sm mt-3 opacity-60 italic").text(t).into_any()
@@ -660,7 +931,7 @@ fn render_node(node: ContentNode) -> AnyView {
                }
             ))
             .into_any(),
-        ContentNode::Table { children } => div()
+        ContentNode::Table { children, .. } => div()
             .class("overflow-x-auto my-10 rounded-xl border border-slate-200 bg-slate-50")
             .child(
                 table()
@@ -679,10 +950,23 @@ fn render_node(node: ContentNode) -> AnyView {
             .class("border-b border-slate-200 hover:bg-slate-100 transition-colors")
             .child(children.into_iter().map(render_node).collect::<Vec<_>>())
             .into_any(),
-        ContentNode::TableCell { children } => td()
-            .class("px-6 py-4 whitespace-pre-wrap")
-            .child(children.into_iter().map(render_node).collect::<Vec<_>>())
-            .into_any(),
+        ContentNode::TableCell {
+            header,
+            align,
+            children,
+        } => {
+            let cell = if header {
+                th().attr("scope", "col").class("px-6 py-4 whitespace-pre-wrap font-bold")
+            } else {
+                td().class("px-6 py-4 whitespace-pre-wrap")
+            };
+            let cell = match align.text_align() {
+                Some(align) => cell.style(format!("text-align: {align}")),
+                None => cell,
+            };
+            cell.child(children.into_iter().map(render_node).collect::<Vec<_>>())
+                .into_any()
+        }
     }
 }
 
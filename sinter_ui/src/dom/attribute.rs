@@ -47,6 +47,20 @@ impl AttributeValue for bool {
     }
 }
 
+// 可选值：`None` 时不设置（并移除已有的）属性
+impl<T: AttributeValue> AttributeValue for Option<T> {
+    fn apply(self, el: &WebElem, name: &str) {
+        match self {
+            Some(value) => value.apply(el, name),
+            None => {
+                if let Err(e) = el.remove_attribute(name).map_err(SinterError::from) {
+                    crate::error::handle_error(e);
+                }
+            }
+        }
+    }
+}
+
 // 2. 动态闭包支持 (Reactive Closure)
 impl<F, S> AttributeValue for F
 where
@@ -232,6 +232,9 @@ pub mod tag {
     pub fn s() -> Element {
         Element::new("s")
     }
+    pub fn sup() -> Element {
+        Element::new("sup")
+    }
     pub fn table() -> Element {
         Element::new("table")
     }
@@ -247,6 +250,18 @@ pub mod tag {
     pub fn td() -> Element {
         Element::new("td")
     }
+    pub fn th() -> Element {
+        Element::new("th")
+    }
+    pub fn caption() -> Element {
+        Element::new("caption")
+    }
+    pub fn colgroup() -> Element {
+        Element::new("colgroup")
+    }
+    pub fn col() -> Element {
+        Element::new("col")
+    }
     pub fn label() -> Element {
         Element::new("label")
     }
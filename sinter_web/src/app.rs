@@ -1,5 +1,5 @@
 use crate::components::layout;
-use crate::pages::{archive_post_view, archives, home, post_view};
+use crate::pages::{archive_post_view, archives, home, post_view, search_view, tag_view};
 use crate::router::{Route, use_router};
 use sinter_theme_sdk::GlobalState;
 use sinter_ui::dom::tag::div;
@@ -49,6 +49,26 @@ pub fn app() -> impl IntoAnyView {
                 });
                 archive_post_view(slug_signal).into_any()
             }
+            Route::Tag(tag_str) => {
+                let tag_signal = create_memo(move || {
+                    if let Some(Route::Tag(t)) = route.get() {
+                        t
+                    } else {
+                        tag_str.clone()
+                    }
+                });
+                tag_view(tag_signal, current_page).into_any()
+            }
+            Route::Search(query_str) => {
+                let query_signal = create_memo(move || {
+                    if let Some(Route::Search(q)) = route.get() {
+                        q
+                    } else {
+                        query_str.clone()
+                    }
+                });
+                search_view(query_signal).into_any()
+            }
             Route::NotFound => div().text("404 - Not Found").into_any(),
         }
     });
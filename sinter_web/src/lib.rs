@@ -69,6 +69,11 @@ pub fn run() {
         let document = window.document().expect("No document");
         let body = document.body().expect("No body");
 
+        // Static HTML written by `sinter build --prerender`; the app replaces it.
+        if let Some(prerendered) = document.get_element_by_id("sinter-prerender") {
+            prerendered.remove();
+        }
+
         let app_view = app();
         app_view.into_any().mount(&body);
     });
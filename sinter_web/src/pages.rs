@@ -1,6 +1,6 @@
-use sinter_core::Post;
 use sinter_theme_sdk::{
-    GlobalState, PageDataContext, fetch_archive_page_data, fetch_json, fetch_page_data,
+    CurrentPageContext, GlobalState, PageDataContext, TagIndexContext, fetch_archive_page_data,
+    fetch_archive_post, fetch_page_data, fetch_post, fetch_tag_index, fetch_tag_page_data,
 };
 use sinter_ui::dom::suspense::suspense;
 use sinter_ui::dom::tag::div;
@@ -52,6 +52,48 @@ pub fn archives(page: ReadSignal<usize>) -> impl IntoAnyView {
     }
 }
 
+pub fn tag_view(tag: ReadSignal<String>, page: ReadSignal<usize>) -> impl IntoAnyView {
+    if let Some(state) = use_context::<GlobalState>() {
+        // Create page data resource (one shard of the tag's posts)
+        let page_data_resource = create_resource(
+            move || (tag.get().unwrap_or_default(), page.get().unwrap_or(1)),
+            |(tag, page_num)| async move { fetch_tag_page_data(&tag, page_num).await },
+        )
+        .expect("Failed to create resource");
+
+        // The tag cloud is only needed here, so it is fetched on first visit
+        let tag_index_resource = create_resource(|| (), |_| async move { fetch_tag_index().await })
+            .expect("Failed to create resource");
+
+        // Provide PageDataContext, the tag cloud and the current page for the theme
+        let _ = provide_context(PageDataContext(page_data_resource));
+        let _ = provide_context(TagIndexContext(tag_index_resource));
+        let _ = provide_context(CurrentPageContext(page));
+
+        Dynamic::new(move || {
+            let theme = state.theme.get().expect("Theme not found");
+            theme.render_tag(tag.get().unwrap_or_default())
+        })
+        .into_any()
+    } else {
+        Dynamic::new(|| div().text("GlobalState missing").into_any()).into_any()
+    }
+}
+
+pub fn search_view(query: ReadSignal<String>) -> impl IntoAnyView {
+    if let Some(state) = use_context::<GlobalState>() {
+        // The theme owns the search box and calls `use_search`; only the
+        // initial query comes from the URL.
+        Dynamic::new(move || {
+            let theme = state.theme.get().expect("Theme not found");
+            theme.render_search(query.get().unwrap_or_default())
+        })
+        .into_any()
+    } else {
+        Dynamic::new(|| div().text("GlobalState missing").into_any()).into_any()
+    }
+}
+
 pub fn post_view(slug: ReadSignal<String>) -> impl IntoAnyView {
     if let Some(state) = use_context::<GlobalState>() {
         let theme_signal = state.theme;
@@ -63,11 +105,7 @@ pub fn post_view(slug: ReadSignal<String>) -> impl IntoAnyView {
                 if current_slug.is_empty() {
                     return None;
                 }
-                let url = format!("/sinter_data/posts/{}.json", current_slug);
-                match fetch_json::<Post>(&url).await {
-                    Ok(post) => Some(post),
-                    Err(_) => None,
-                }
+                fetch_post(&current_slug).await.ok()
             },
         )
         .expect("Failed to create resource");
@@ -106,11 +144,7 @@ pub fn archive_post_view(slug: ReadSignal<String>) -> impl IntoAnyView {
                 if current_slug.is_empty() {
                     return None;
                 }
-                let url = format!("/sinter_data/archives/{}.json", current_slug);
-                match fetch_json::<Post>(&url).await {
-                    Ok(post) => Some(post),
-                    Err(_) => None,
-                }
+                fetch_archive_post(&current_slug).await.ok()
             },
         )
         .expect("Failed to create resource");
@@ -9,13 +9,30 @@ pub enum Route {
     Archives,
     Post(String),
     ArchivePost(String),
+    Tag(String),
+    Search(String),
     NotFound,
 }
 
+/// Splits a prerendered pagination path (`/page/2`, `/archives/page/2`) into
+/// the listing path and page number.
+fn split_page_path(path: &str) -> Option<(&str, usize)> {
+    let (base, page) = path.trim_end_matches('/').rsplit_once("/page/")?;
+    let page = page.parse().ok()?;
+    Some((if base.is_empty() { "/" } else { base }, page))
+}
+
 impl Route {
-    fn from_path(path: &str) -> Self {
+    fn from_location(path: &str, search: &str) -> Self {
+        let path = split_page_path(path).map_or(path, |(base, _)| base);
         if path == "/" || path == "/index.html" {
             Route::Home
+        } else if path == "/search" || path == "/search/" {
+            let query = web_sys::UrlSearchParams::new_with_str(search)
+                .ok()
+                .and_then(|p| p.get("q"))
+                .unwrap_or_default();
+            Route::Search(query)
         } else if path == "/archives" || path == "/archives/" {
             Route::Archives
         } else if let Some(slug) = path.strip_prefix("/posts/") {
@@ -32,6 +49,17 @@ impl Route {
             } else {
                 Route::ArchivePost(slug.to_string())
             }
+        } else if let Some(tag) = path.strip_prefix("/tags/") {
+            // `location.pathname` is percent-encoded; tag slugs may be non-ASCII.
+            let tag = tag.trim_matches('/');
+            let tag = js_sys::decode_uri_component(tag)
+                .map(String::from)
+                .unwrap_or_else(|_| tag.to_string());
+            if tag.is_empty() {
+                Route::NotFound
+            } else {
+                Route::Tag(tag)
+            }
         } else {
             Route::NotFound
         }
@@ -96,6 +124,13 @@ pub fn use_router() -> (ReadSignal<Route>, ReadSignal<usize>) {
                 if let Ok(url) = Url::new(&href) {
                     // Check if it's the same origin
                     if let Ok(origin) = web_sys::window().unwrap().location().origin() {
+                        // In-page anchors (e.g. footnotes) are left to the browser.
+                        let location = web_sys::window().unwrap().location();
+                        let same_page = location.pathname().is_ok_and(|p| p == url.pathname())
+                            && location.search().is_ok_and(|s| s == url.search());
+                        if same_page && !url.hash().is_empty() {
+                            return;
+                        }
                         if url.origin() == origin {
                             ev.prevent_default();
                             let pathname = url.pathname();
@@ -127,7 +162,12 @@ pub fn use_router() -> (ReadSignal<Route>, ReadSignal<usize>) {
         });
     });
 
-    let current_route = create_memo(move || Route::from_path(&path.get().unwrap_or_default()));
+    let current_route = create_memo(move || {
+        Route::from_location(
+            &path.get().unwrap_or_default(),
+            &search.get().unwrap_or_default(),
+        )
+    });
 
     let current_page = create_memo(move || {
         let s = search.get().unwrap_or_default();
@@ -135,6 +175,7 @@ pub fn use_router() -> (ReadSignal<Route>, ReadSignal<usize>) {
             .ok()
             .and_then(|p| p.get("page"))
             .and_then(|p_str| p_str.parse::<usize>().ok())
+            .or_else(|| split_page_path(&path.get().unwrap_or_default()).map(|(_, page)| page))
             .unwrap_or(1)
     });
 